@@ -0,0 +1,71 @@
+// 对比开启和关闭预处理语句缓存时 10000 次 select_user_by_id 的耗时
+//
+// 运行方式: TEST_DATABASE_URL=mysql://... cargo bench --bench statement_cache
+// 会在该 MySQL 实例上临时创建 sqlx_example_bench 数据库，结束后删除
+use std::env;
+use std::str::FromStr;
+use std::time::Instant;
+
+use anyhow::Result;
+use sqlx::mysql::{MySqlConnectOptions, MySqlPoolOptions};
+
+use sqlx_example::UserService;
+use sqlx_example::database::select_user_by_id;
+use sqlx_example::migrations::run_migrations;
+
+const LOOKUPS: usize = 10_000;
+const BENCH_DATABASE: &str = "sqlx_example_bench";
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let Ok(url) = env::var("TEST_DATABASE_URL") else {
+        println!("未设置 TEST_DATABASE_URL，跳过基准测试");
+        return Ok(());
+    };
+
+    let admin = MySqlPoolOptions::new().max_connections(1).connect(&url).await?;
+    sqlx::query(&format!("CREATE DATABASE IF NOT EXISTS `{}`", BENCH_DATABASE))
+        .execute(&admin)
+        .await?;
+
+    let result = run(&url).await;
+
+    sqlx::query(&format!("DROP DATABASE IF EXISTS `{}`", BENCH_DATABASE))
+        .execute(&admin)
+        .await?;
+    result
+}
+
+async fn run(url: &str) -> Result<()> {
+    let mut user_id = None;
+    for capacity in [0, 100] {
+        // 只用一个连接，保证每次查询都命中同一个连接的缓存
+        let options = MySqlConnectOptions::from_str(url)?
+            .database(BENCH_DATABASE)
+            .statement_cache_capacity(capacity);
+        let pool = MySqlPoolOptions::new().max_connections(1).connect_with(options).await?;
+
+        let id = match user_id {
+            Some(id) => id,
+            None => {
+                run_migrations(&pool).await?;
+                *user_id.insert(UserService::insert_user(&pool).await?)
+            }
+        };
+
+        let start = Instant::now();
+        for _ in 0..LOOKUPS {
+            select_user_by_id(&pool, id).await?;
+        }
+        let elapsed = start.elapsed();
+        println!(
+            "statement_cache_capacity = {:>3}: {} 次查询耗时 {:?}，平均 {:?}/次",
+            capacity,
+            LOOKUPS,
+            elapsed,
+            elapsed / LOOKUPS as u32
+        );
+        pool.close().await;
+    }
+    Ok(())
+}
@@ -0,0 +1,271 @@
+use anyhow::Result;
+use tracing::{debug, error, info, warn};
+
+use crate::database::{
+    AppPools, WritePool, check_autoinc_settings, select_all_users, select_user_by_email, select_user_by_id,
+    select_user_by_username, select_users_paginated,
+};
+use crate::migrations::run_migrations;
+use crate::services::{UserProfileService, UserService};
+use crate::utils::{generate_default_username, generate_random_email_default};
+
+// 演示流程中每个步骤完成后产生的事件，按发生顺序由 run_demo 返回，便于界面或日志管道处理
+// 失败的步骤只记录日志，不产生事件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DemoEvent {
+    // 迁移执行完成，用户表和 profile 表已存在
+    TableCreated,
+    UserInserted { id: u64 },
+    UsersBatchInserted { ids: Vec<u64> },
+    UserUpserted { id: u64, created: bool },
+    UsersQueried { count: usize },
+    UserUpdated { id: u64 },
+    OldestUserDeleted,
+    UserWithProfileCreated { user_id: u64, profile_id: u64 },
+    // 用户和 profile 的更新语句都匹配到了记录
+    UserAndProfileUpdated { id: u64 },
+    // 回滚演示中事务确实被回滚，multi_table 表示是否为多表事务
+    RollbackTriggered { multi_table: bool },
+    UserAndProfileDeleted { id: u64 },
+    // 最终验证时数据库中的用户和 profile 数量
+    Verified { users: usize, profiles: usize },
+}
+
+// 演示流程的汇总：创建、更新、删除的用户数量和触发的回滚次数
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RunSummary {
+    pub users_created: usize,
+    pub users_updated: usize,
+    pub users_deleted: usize,
+    pub rollbacks: usize,
+}
+
+impl RunSummary {
+    // 按事件累加计数，插入或更新时按是否新建分别计入创建或更新
+    pub fn record(&mut self, event: &DemoEvent) {
+        match event {
+            DemoEvent::UserInserted { .. } | DemoEvent::UserWithProfileCreated { .. } => {
+                self.users_created += 1
+            }
+            DemoEvent::UsersBatchInserted { ids } => self.users_created += ids.len(),
+            DemoEvent::UserUpserted { created: true, .. } => self.users_created += 1,
+            DemoEvent::UserUpserted { created: false, .. }
+            | DemoEvent::UserUpdated { .. }
+            | DemoEvent::UserAndProfileUpdated { .. } => self.users_updated += 1,
+            DemoEvent::OldestUserDeleted | DemoEvent::UserAndProfileDeleted { .. } => {
+                self.users_deleted += 1
+            }
+            DemoEvent::RollbackTriggered { .. } => self.rollbacks += 1,
+            DemoEvent::TableCreated | DemoEvent::UsersQueried { .. } | DemoEvent::Verified { .. } => {}
+        }
+    }
+}
+
+// run_demo 的结果：按顺序产生的事件和随之累加的汇总
+#[derive(Debug, Default)]
+pub struct DemoRun {
+    pub events: Vec<DemoEvent>,
+    pub summary: RunSummary,
+}
+
+impl DemoRun {
+    pub fn record(&mut self, event: DemoEvent) {
+        self.summary.record(&event);
+        self.events.push(event);
+    }
+}
+
+// 完整的演示流程：建表、增删改查、多表事务和回滚测试，按顺序返回各步骤产生的事件和汇总
+// 演示中的查询都需要看到演示自己的写入，因此通过 read_primary() 全部使用主库（表名前缀和查询超时仍取自 pools）
+pub async fn run_demo(pools: &AppPools) -> Result<DemoRun> {
+    let pool = pools.read_primary();
+    let mut run = DemoRun::default();
+
+    // 1. 创建表 - 通过迁移按顺序创建用户表和 profile 表
+    run_migrations(&pool).await?;
+    info!("用户表和 profile 表创建/检查完成");
+    run.record(DemoEvent::TableCreated);
+
+    // 2. 插入数据（使用事务确保提交，失败时回滚）
+    let user_id = UserService::insert_user(&pool).await?;
+    info!("插入用户成功，ID: {}", user_id);
+    run.record(DemoEvent::UserInserted { id: user_id });
+
+    // 批量插入数据（单条多行 INSERT，返回的ID按第一行ID推算，先检查自增配置）
+    check_autoinc_settings(pool.write_pool()).await?;
+    let batch: Vec<(String, String)> = (0..3)
+        .map(|_| (generate_default_username(), generate_random_email_default()))
+        .collect();
+    let batch_ids = UserService::insert_users_batch(&pool, &batch).await?;
+    info!("批量插入用户成功，ID: {:?}", batch_ids);
+    run.record(DemoEvent::UsersBatchInserted { ids: batch_ids });
+
+    // 插入或更新数据 - 重复用户名时只更新邮箱
+    let (upsert_id, created) =
+        UserService::upsert_user(&pool, &batch[0].0, &generate_random_email_default()).await?;
+    info!("插入或更新用户成功，ID: {}, 是否新建: {}", upsert_id, created);
+    run.record(DemoEvent::UserUpserted { id: upsert_id, created });
+
+    // 3. 查询所有数据 - 需要读到刚才的写入，直接查询主库，避免只读副本的复制延迟
+    let users = select_all_users(&pool).await?;
+    info!("查询到 {} 个用户", users.len());
+    run.record(DemoEvent::UsersQueried { count: users.len() });
+    for user in &users {
+        debug!(
+            "用户详情 - ID: {}, 用户名: {}, 邮箱: {}, 创建时间: {}, 更新时间: {}",
+            user.id, user.username, user.email, user.created_at, user.updated_at
+        );
+    }
+
+    // 分页查询第一页数据
+    let page = select_users_paginated(&pool, 10, 0).await?;
+    info!("分页查询 - 第一页 {} 个用户，共 {} 个用户", page.data.len(), page.total);
+
+    // 4. 根据ID查询数据
+    if let Some(user) = select_user_by_id(&pool, user_id).await? {
+        info!(
+            "根据ID查询用户成功 - ID: {}, 用户名: {}, 邮箱: {}",
+            user.id, user.username, user.email
+        );
+
+        // 根据用户名和邮箱查询数据
+        if let Some(found) = select_user_by_username(&pool, &user.username).await? {
+            info!("根据用户名查询用户成功 - ID: {}, 用户名: {}", found.id, found.username);
+        }
+        if let Some(found) = select_user_by_email(&pool, &user.email).await? {
+            info!("根据邮箱查询用户成功 - ID: {}, 邮箱: {}", found.id, found.email);
+        }
+    } else {
+        warn!("未找到ID为 {} 的用户", user_id);
+    }
+
+    // 5. 更新操作 - 只更新邮箱（使用事务确保提交，失败时回滚）
+    match UserService::update_user_email(&pool, user_id).await {
+        Ok(_) => run.record(DemoEvent::UserUpdated { id: user_id }),
+        Err(e) => error!("更新用户失败: {}", e),
+    }
+
+    // 6. 删除操作 - 删除最早写入的用户（使用事务确保提交，失败时回滚）
+    match UserService::delete_oldest_user(&pool).await {
+        Ok(()) => run.record(DemoEvent::OldestUserDeleted),
+        Err(e) => warn!("删除用户失败: {}", e),
+    }
+
+    // 7. 多表事务操作演示 - 同时创建用户和 profile
+    info!("开始多表事务操作演示...");
+    let mut profile_user_id = None;
+    match UserProfileService::create_user_with_profile(&pool).await {
+        Ok((user_id, profile_id)) => {
+            profile_user_id = Some(user_id);
+            info!("多表事务创建成功 - 用户ID: {}, Profile ID: {}", user_id, profile_id);
+            run.record(DemoEvent::UserWithProfileCreated { user_id, profile_id });
+            
+            // 验证创建的数据
+            if let Some(user) = select_user_by_id(&pool, user_id).await? {
+                info!("创建的用户 - ID: {}, 用户名: {}, 邮箱: {}",
+                    user.id, user.username, user.email);
+            }
+            
+            if let Some(profile) = crate::database::select_profile_by_user_id(&pool, user_id).await? {
+                info!("创建的 Profile - ID: {}, 用户ID: {}, 全名: {}, 简介: {:?}",
+                    profile.id, profile.user_id, profile.full_name, profile.bio);
+            }
+        }
+        Err(e) => {
+            error!("多表事务创建失败: {}", e);
+        }
+    }
+
+    // 8. 多表事务更新演示 - 更新上一步创建的用户及其 profile，两条语句都匹配到记录时才算更新成功
+    if let Some(user_id) = profile_user_id {
+        match UserProfileService::update_user_and_profile(&pool, user_id).await {
+            Ok(report) => {
+                info!(
+                    "多表事务更新完成 - 用户: {} 行, profile: {} 行",
+                    report.user_rows, report.profile_rows
+                );
+                if report.user_rows > 0 && report.profile_rows > 0 {
+                    run.record(DemoEvent::UserAndProfileUpdated { id: user_id });
+                }
+            }
+            Err(e) => warn!("多表事务更新失败: {}", e),
+        }
+    }
+
+    // 9. 事务回滚测试 - 故意插入重复数据来演示回滚
+    // 只有事务确实被回滚时才记录事件，意外提交时不计入
+    match crate::services::test_transaction_rollback(&pool).await {
+        Ok(true) => run.record(DemoEvent::RollbackTriggered { multi_table: false }),
+        Ok(false) => warn!("事务回滚测试没有触发回滚"),
+        Err(e) => warn!("事务回滚测试失败: {}", e),
+    }
+    match UserProfileService::test_multi_table_transaction_rollback(&pool).await {
+        Ok(true) => run.record(DemoEvent::RollbackTriggered { multi_table: true }),
+        Ok(false) => warn!("多表事务回滚测试没有触发回滚"),
+        Err(e) => warn!("多表事务回滚测试失败: {}", e),
+    }
+
+    // 10. 多表事务删除演示 - 删除最早的用户及其 profile
+    if let Some(user) = crate::database::find_oldest_user(&pool).await? {
+        match UserProfileService::delete_user_and_profile(&pool, user.id).await {
+            Ok(()) => run.record(DemoEvent::UserAndProfileDeleted { id: user.id }),
+            Err(e) => warn!("多表事务删除失败: {}", e),
+        }
+    }
+
+    // 11. 最终验证 - 查询所有数据确认数据持久化
+    info!("最终验证 - 查询数据库中的所有用户:");
+    let final_users = select_all_users(&pool).await?;
+    info!("数据库中实际存在的用户数量: {}", final_users.len());
+    for user in &final_users {
+        info!(
+            "最终用户数据 - ID: {}, 用户名: {}, 邮箱: {}",
+            user.id, user.username, user.email
+        );
+    }
+
+    info!("最终验证 - 查询数据库中的所有 profiles:");
+    let final_profiles = crate::database::select_all_profiles(&pool).await?;
+    info!("数据库中实际存在的 profile 数量: {}", final_profiles.len());
+    for profile in &final_profiles {
+        info!(
+            "最终 Profile 数据 - ID: {}, 用户ID: {}, 全名: {}, 简介: {:?}",
+            profile.id, profile.user_id, profile.full_name, profile.bio
+        );
+    }
+
+    run.record(DemoEvent::Verified { users: final_users.len(), profiles: final_profiles.len() });
+
+    info!("SQLx MySQL 示例程序执行完成 - 所有事务操作（包括多表事务和回滚测试）已完成");
+    Ok(run)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_summary_counts_events() {
+        let mut run = DemoRun::default();
+        for event in [
+            DemoEvent::TableCreated,
+            DemoEvent::UserInserted { id: 1 },
+            DemoEvent::UsersBatchInserted { ids: vec![2, 3, 4] },
+            DemoEvent::UserUpserted { id: 2, created: false },
+            DemoEvent::UserUpdated { id: 1 },
+            DemoEvent::OldestUserDeleted,
+            DemoEvent::UserWithProfileCreated { user_id: 5, profile_id: 1 },
+            DemoEvent::RollbackTriggered { multi_table: false },
+            DemoEvent::RollbackTriggered { multi_table: true },
+            DemoEvent::UserAndProfileDeleted { id: 2 },
+        ] {
+            run.record(event);
+        }
+
+        assert_eq!(run.events.len(), 10);
+        assert_eq!(
+            run.summary,
+            RunSummary { users_created: 5, users_updated: 2, users_deleted: 2, rollbacks: 2 }
+        );
+    }
+}
@@ -0,0 +1,218 @@
+use sqlx::mysql::MySqlDatabaseError;
+use thiserror::Error;
+
+// MySQL 错误码：唯一键冲突
+pub const ER_DUP_ENTRY: u16 = 1062;
+
+// MySQL 错误码：表不存在
+pub const ER_NO_SUCH_TABLE: u16 = 1146;
+
+// MySQL 错误码：删除或更新的记录仍被其他表的外键引用（1217 为不带约束名的旧版本）
+pub const ER_ROW_IS_REFERENCED: u16 = 1217;
+pub const ER_ROW_IS_REFERENCED_2: u16 = 1451;
+
+// MySQL 错误码：插入或更新的外键在被引用的表中不存在（1216 为不带约束名的旧版本）
+pub const ER_NO_REFERENCED_ROW: u16 = 1216;
+pub const ER_NO_REFERENCED_ROW_2: u16 = 1452;
+
+// MySQL 错误码：等待行锁超时
+pub const ER_LOCK_WAIT_TIMEOUT: u16 = 1205;
+
+// MySQL 错误码：检测到死锁，事务已被回滚
+pub const ER_LOCK_DEADLOCK: u16 = 1213;
+
+// 应用错误类型
+#[derive(Debug, Error)]
+pub enum AppError {
+    // 数据库错误
+    #[error("数据库错误: {0}")]
+    Database(#[source] sqlx::Error),
+
+    // 数据表不存在，通常是还没有执行迁移
+    #[error("数据表不存在: {0}，请先执行 run_migrations() 创建表")]
+    SchemaMissing(String),
+
+    // 违反外键约束
+    #[error("违反外键约束: {0}")]
+    ForeignKeyViolation(String),
+
+    // 记录不存在
+    #[error("未找到: {0}")]
+    NotFound(String),
+
+    // 输入校验失败
+    #[error("校验失败: {0}")]
+    Validation(String),
+
+    // 违反唯一约束
+    #[error("数据重复: {0}")]
+    Duplicate(String),
+
+    // 配置缺失或无效，例如未设置 DATABASE_URL
+    #[error("配置错误: {0}")]
+    Config(String),
+
+    // 查询超时
+    #[error("查询超时: 超过 {0:?} 未完成")]
+    Timeout(std::time::Duration),
+}
+
+// 表不存在（1146）映射为 SchemaMissing，外键错误（1451 / 1452 等）映射为 ForeignKeyViolation，
+// 其余错误保留为 Database
+impl From<sqlx::Error> for AppError {
+    fn from(e: sqlx::Error) -> Self {
+        let number = mysql_error_number(&e);
+        match e.as_database_error() {
+            Some(db_err) if number == Some(ER_NO_SUCH_TABLE) => {
+                AppError::SchemaMissing(db_err.message().to_string())
+            }
+            // 外键错误按 sqlx 的错误分类判断，错误码只用于选择提示信息
+            Some(db_err) if db_err.is_foreign_key_violation() => {
+                AppError::ForeignKeyViolation(foreign_key_message(number, db_err.message()))
+            }
+            _ => AppError::Database(e),
+        }
+    }
+}
+
+// 外键错误的提示信息：区分 "记录仍被引用" 和 "引用的记录不存在"
+fn foreign_key_message(number: Option<u16>, message: &str) -> String {
+    match number {
+        Some(ER_ROW_IS_REFERENCED | ER_ROW_IS_REFERENCED_2) => {
+            format!("记录仍被其他记录引用，需要先删除引用它的记录 ({})", message)
+        }
+        Some(ER_NO_REFERENCED_ROW | ER_NO_REFERENCED_ROW_2) => format!("引用的记录不存在 ({})", message),
+        _ => message.to_string(),
+    }
+}
+
+// 将 anyhow 错误中直接包含的 sqlx 错误转换为 AppError，其他错误原样返回
+// 用于事务闭包等通过 ? 把 sqlx 错误直接转换为 anyhow 错误的地方
+pub fn map_sqlx_error(e: anyhow::Error) -> anyhow::Error {
+    match e.downcast::<sqlx::Error>() {
+        Ok(sqlx_err) => AppError::from(sqlx_err).into(),
+        Err(e) => e,
+    }
+}
+
+// 提取 sqlx 错误中的 MySQL 错误码
+pub fn mysql_error_number(e: &sqlx::Error) -> Option<u16> {
+    e.as_database_error()
+        .and_then(|db_err| db_err.try_downcast_ref::<MySqlDatabaseError>())
+        .map(|mysql_err| mysql_err.number())
+}
+
+// 判断是否为唯一键冲突错误
+pub fn is_duplicate_key(e: &sqlx::Error) -> bool {
+    mysql_error_number(e) == Some(ER_DUP_ENTRY)
+}
+
+// 判断 anyhow 错误是否为唯一键冲突，同时识别直接的 sqlx::Error 和包装后的 AppError::Database
+pub fn is_duplicate_key_error(e: &anyhow::Error) -> bool {
+    sqlx_error_of(e).is_some_and(is_duplicate_key)
+}
+
+// 判断错误是否为死锁（1213）或锁等待超时（1205），这类错误重新执行整个事务通常可以成功
+// 同时识别直接的 sqlx::Error 和包装后的 AppError::Database
+pub fn is_retryable_lock_error(e: &anyhow::Error) -> bool {
+    sqlx_error_of(e)
+        .and_then(mysql_error_number)
+        .is_some_and(is_lock_conflict)
+}
+
+// 取出 anyhow 错误中的 sqlx 错误：直接的 sqlx::Error 或 AppError::Database 中包装的错误
+fn sqlx_error_of(e: &anyhow::Error) -> Option<&sqlx::Error> {
+    match e.downcast_ref::<AppError>() {
+        Some(AppError::Database(inner)) => Some(inner),
+        Some(_) => None,
+        None => e.downcast_ref::<sqlx::Error>(),
+    }
+}
+
+fn is_lock_conflict(number: u16) -> bool {
+    matches!(number, ER_LOCK_DEADLOCK | ER_LOCK_WAIT_TIMEOUT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::error::{DatabaseError, ErrorKind};
+
+    // 无法在 sqlx 外部构造 MySqlDatabaseError，用只带错误分类的数据库错误模拟驱动返回的错误
+    #[derive(Debug)]
+    struct SyntheticDatabaseError {
+        message: &'static str,
+        foreign_key: bool,
+    }
+
+    impl std::fmt::Display for SyntheticDatabaseError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str(self.message)
+        }
+    }
+
+    impl std::error::Error for SyntheticDatabaseError {}
+
+    impl DatabaseError for SyntheticDatabaseError {
+        fn message(&self) -> &str {
+            self.message
+        }
+
+        fn as_error(&self) -> &(dyn std::error::Error + Send + Sync + 'static) {
+            self
+        }
+
+        fn as_error_mut(&mut self) -> &mut (dyn std::error::Error + Send + Sync + 'static) {
+            self
+        }
+
+        fn into_error(self: Box<Self>) -> Box<dyn std::error::Error + Send + Sync + 'static> {
+            self
+        }
+
+        fn kind(&self) -> ErrorKind {
+            if self.foreign_key { ErrorKind::ForeignKeyViolation } else { ErrorKind::Other }
+        }
+    }
+
+    fn database_error(message: &'static str, foreign_key: bool) -> sqlx::Error {
+        sqlx::Error::Database(Box::new(SyntheticDatabaseError { message, foreign_key }))
+    }
+
+    #[test]
+    fn test_foreign_key_errors_are_mapped() {
+        let message = "Cannot delete or update a parent row: a foreign key constraint fails";
+        let err = AppError::from(database_error(message, true));
+        assert!(matches!(err, AppError::ForeignKeyViolation(m) if m.contains(message)));
+
+        let err = AppError::from(database_error("Duplicate entry", false));
+        assert!(matches!(err, AppError::Database(sqlx::Error::Database(_))));
+
+        // 经 anyhow 传递的 sqlx 错误同样会被映射
+        let err = map_sqlx_error(database_error(message, true).into());
+        assert!(matches!(err.downcast_ref::<AppError>(), Some(AppError::ForeignKeyViolation(_))));
+    }
+
+    #[test]
+    fn test_foreign_key_message_by_error_number() {
+        assert!(foreign_key_message(Some(ER_ROW_IS_REFERENCED_2), "fk").starts_with("记录仍被其他记录引用"));
+        assert!(foreign_key_message(Some(ER_NO_REFERENCED_ROW_2), "fk").starts_with("引用的记录不存在"));
+        assert_eq!(foreign_key_message(None, "fk"), "fk");
+    }
+
+    #[test]
+    fn test_lock_conflicts_are_retryable() {
+        assert!(is_lock_conflict(ER_LOCK_DEADLOCK));
+        assert!(is_lock_conflict(ER_LOCK_WAIT_TIMEOUT));
+        assert!(!is_lock_conflict(ER_DUP_ENTRY));
+        assert!(!is_retryable_lock_error(&anyhow::Error::from(sqlx::Error::RowNotFound)));
+        assert!(!is_retryable_lock_error(&AppError::NotFound("用户".to_string()).into()));
+    }
+
+    #[test]
+    fn test_non_mysql_error_stays_database() {
+        // 无法在 sqlx 外部构造 MySqlDatabaseError，这里用没有错误码的 sqlx 错误验证兜底分支
+        let err = AppError::from(sqlx::Error::RowNotFound);
+        assert!(matches!(err, AppError::Database(sqlx::Error::RowNotFound)));
+    }
+}
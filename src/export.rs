@@ -0,0 +1,114 @@
+use anyhow::Result;
+use futures_util::TryStreamExt;
+use std::io::Write;
+use tracing::{debug, info};
+
+use crate::database::{
+    ReadPool, select_all_profiles, select_all_users, select_users_paginated, stream_all_users,
+};
+
+// CSV 表头
+const USERS_CSV_HEADER: &str = "id,username,email,created_at,updated_at";
+
+// 以 CSV 格式导出所有用户，逐行流式读取，不会把整张表加载到内存
+#[tracing::instrument(skip(writer))]
+pub async fn export_users_csv(pool: &impl ReadPool, mut writer: impl Write) -> Result<()> {
+    info!("开始导出用户 CSV");
+    writeln!(writer, "{}", USERS_CSV_HEADER)?;
+
+    let mut rows = stream_all_users(pool);
+    let mut count = 0u64;
+    while let Some(user) = rows.try_next().await? {
+        writeln!(
+            writer,
+            "{},{},{},{},{}",
+            user.id,
+            csv_field(&user.username),
+            csv_field(&user.email),
+            user.created_at.to_rfc3339(),
+            user.updated_at.to_rfc3339()
+        )?;
+        count += 1;
+    }
+    writer.flush()?;
+
+    debug!("CSV 导出写入 {} 行", count);
+    info!("用户 CSV 导出完成");
+    Ok(())
+}
+
+// 以格式化 JSON 数组导出所有用户
+#[tracing::instrument]
+pub async fn export_users_json(pool: &impl ReadPool) -> Result<String> {
+    let users = select_all_users(pool).await?;
+    let json = serde_json::to_string_pretty(&users)?;
+    info!("用户 JSON 导出完成 - {} 个用户", users.len());
+    Ok(json)
+}
+
+// 查询一页用户并序列化为 REST 接口使用的 JSON 封装 { "data": [...], "total": N, "limit": L, "offset": O }
+// 只是 select_users_paginated 与 Page 序列化的组合，limit 为 select_users_paginated 截断到单页上限后实际使用的值
+#[tracing::instrument]
+pub async fn users_page_json(pool: &impl ReadPool, limit: u64, offset: u64) -> Result<String> {
+    let page = select_users_paginated(pool, limit, offset).await?;
+    let json = serde_json::to_string(&page)?;
+    debug!("用户分页 JSON - 本页 {} 个用户，总数: {}", page.data.len(), page.total);
+    Ok(json)
+}
+
+// 以格式化 JSON 数组导出所有 profiles
+#[tracing::instrument]
+pub async fn export_profiles_json(pool: &impl ReadPool) -> Result<String> {
+    let profiles = select_all_profiles(pool).await?;
+    let json = serde_json::to_string_pretty(&profiles)?;
+    info!("profile JSON 导出完成 - {} 个 profiles", profiles.len());
+    Ok(json)
+}
+
+// 以 JSON 数组流式导出所有用户，每读取一行就写出一个元素，不会先构建完整的 Vec
+#[tracing::instrument(skip(writer))]
+pub async fn export_users_json_stream(pool: &impl ReadPool, mut writer: impl Write) -> Result<()> {
+    info!("开始流式导出用户 JSON");
+    write!(writer, "[")?;
+
+    let mut rows = stream_all_users(pool);
+    let mut count = 0u64;
+    while let Some(user) = rows.try_next().await? {
+        if count > 0 {
+            write!(writer, ",")?;
+        }
+        write!(writer, "\n  ")?;
+        serde_json::to_writer(&mut writer, &user)?;
+        count += 1;
+    }
+    if count > 0 {
+        writeln!(writer)?;
+    }
+    writeln!(writer, "]")?;
+    writer.flush()?;
+
+    debug!("JSON 流式导出写入 {} 个用户", count);
+    info!("用户 JSON 流式导出完成");
+    Ok(())
+}
+
+// 包含逗号、引号或换行的字段用双引号包裹，内部引号加倍
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_field_quoting() {
+        assert_eq!(csv_field("alice"), "alice");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+}
@@ -0,0 +1,18 @@
+// SQLx MySQL 示例库 - 提供数据库访问、模型定义和服务层
+pub mod database;
+pub mod demo;
+pub mod error;
+pub mod export;
+pub mod migrations;
+pub mod models;
+pub mod repository;
+pub mod schema;
+pub mod services;
+pub mod utils;
+
+// 重新导出常用类型
+pub use demo::{DemoEvent, DemoRun, RunSummary, run_demo};
+pub use error::AppError;
+pub use models::{Profile, User, UserEpoch};
+pub use repository::Repository;
+pub use services::{ProfileService, UserProfileService, UserService};
@@ -1,124 +1,122 @@
 use anyhow::Result;
-use tracing::{Level, debug, error, info, warn};
-use tracing_subscriber;
+use tracing::{debug, info, warn};
+use tracing_subscriber::EnvFilter;
+
+use sqlx_example::database::{AppPools, PoolConfig, health_check, log_pool_status, select_all_users};
+use sqlx_example::migrations::run_migrations;
+use sqlx_example::{AppError, UserService, run_demo};
+
+// seed 操作默认插入的用户数量
+const DEFAULT_SEED_COUNT: usize = 10;
+
+const USAGE: &str = "用法: sqlx-example [--op demo|seed|list|migrate] [--count N]
+
+  --op demo      执行完整的演示流程（默认）
+  --op seed      插入 --count 个随机用户（默认 10 个）
+  --op list      输出所有用户
+  --op migrate   只执行数据库迁移";
+
+// 命令行指定的操作，不带参数时执行完整演示
+#[derive(Debug, PartialEq, Eq)]
+enum Command {
+    Demo,
+    Seed { count: usize },
+    List,
+    Migrate,
+    Help,
+}
+
+// 解析命令行参数（不含程序名）
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Command> {
+    let mut op = None;
+    let mut count = None;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--op" => op = Some(args.next().ok_or_else(|| invalid_arg("--op 缺少参数值"))?),
+            "--count" => {
+                let value = args.next().ok_or_else(|| invalid_arg("--count 缺少参数值"))?;
+                let parsed = value
+                    .parse()
+                    .map_err(|_| invalid_arg(&format!("--count 必须是非负整数: {}", value)))?;
+                count = Some(parsed);
+            }
+            "-h" | "--help" => return Ok(Command::Help),
+            other => return Err(invalid_arg(&format!("未知参数: {}", other)).into()),
+        }
+    }
 
-// 导入模块
-mod models;
-mod database;
-mod services;
-mod utils;
+    let command = match op.as_deref().unwrap_or("demo") {
+        "demo" => Command::Demo,
+        "seed" => Command::Seed { count: count.unwrap_or(DEFAULT_SEED_COUNT) },
+        "list" => Command::List,
+        "migrate" => Command::Migrate,
+        other => return Err(invalid_arg(&format!("未知操作: {}", other)).into()),
+    };
+    if count.is_some() && !matches!(command, Command::Seed { .. }) {
+        return Err(invalid_arg("--count 只能和 --op seed 一起使用").into());
+    }
+    Ok(command)
+}
 
-use crate::database::{create_pool, create_table, select_all_users, select_user_by_id};
-use crate::services::{UserService, UserProfileService};
+fn invalid_arg(message: &str) -> AppError {
+    AppError::Validation(format!("{}\n\n{}", message, USAGE))
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // 初始化日志系统
+    // 初始化日志系统 - 通过 RUST_LOG 控制日志级别，未设置时默认为 info
+    let env_filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
     tracing_subscriber::fmt()
-        .with_max_level(Level::INFO)
+        .with_env_filter(env_filter)
         .with_target(false)
         .init();
 
-    info!("启动 SQLx MySQL 示例程序");
-
-    // 1. 创建数据库连接池
-    let pool = create_pool().await?;
-
-    // 2. 创建表
-    create_table(&pool).await?;
-    crate::database::create_profile_table(&pool).await?;
-    info!("用户表和 profile 表创建/检查完成");
-
-    // 3. 插入数据（使用事务确保提交，失败时回滚）
-    let user_id = UserService::insert_user(&pool).await?;
-    info!("插入用户成功，ID: {}", user_id);
-
-    // 4. 查询所有数据
-    let users = select_all_users(&pool).await?;
-    info!("查询到 {} 个用户", users.len());
-    for user in &users {
-        debug!(
-            "用户详情 - ID: {}, 用户名: {}, 邮箱: {}, 创建时间: {}, 更新时间: {}",
-            user.id, user.username, user.email, user.created_at, user.updated_at
-        );
-    }
-
-    // 5. 根据ID查询数据
-    if let Some(user) = select_user_by_id(&pool, user_id).await? {
-        info!(
-            "根据ID查询用户成功 - ID: {}, 用户名: {}, 邮箱: {}",
-            user.id, user.username, user.email
-        );
-    } else {
-        warn!("未找到ID为 {} 的用户", user_id);
+    let command = parse_args(std::env::args().skip(1))?;
+    if command == Command::Help {
+        println!("{}", USAGE);
+        return Ok(());
     }
 
-    // 6. 更新操作 - 只更新邮箱（使用事务确保提交，失败时回滚）
-    if let Err(e) = UserService::update_user_email(&pool, user_id).await {
-        error!("更新用户失败: {}", e);
-    }
+    info!("启动 SQLx MySQL 示例程序");
 
-    // 7. 删除操作 - 删除最早写入的用户（使用事务确保提交，失败时回滚）
-    if let Err(e) = UserService::delete_oldest_user(&pool).await {
-        warn!("删除用户失败: {}", e);
+    // 创建数据库连接池 - 写操作使用主库，读查询在设置了 REPLICA_DATABASE_URL 时使用只读副本
+    let pools = AppPools::connect(&PoolConfig::from_env()).await?;
+    if !health_check(&pools.primary).await? {
+        warn!("数据库健康检查未通过");
     }
 
-    // 8. 多表事务操作演示 - 同时创建用户和 profile
-    info!("开始多表事务操作演示...");
-    match UserProfileService::create_user_with_profile(&pool).await {
-        Ok((user_id, profile_id)) => {
-            info!("多表事务创建成功 - 用户ID: {}, Profile ID: {}", user_id, profile_id);
-            
-            // 验证创建的数据
-            if let Some(user) = select_user_by_id(&pool, user_id).await? {
-                info!("创建的用户 - ID: {}, 用户名: {}, 邮箱: {}",
-                    user.id, user.username, user.email);
-            }
-            
-            if let Some(profile) = crate::database::select_profile_by_user_id(&pool, user_id).await? {
-                info!("创建的 Profile - ID: {}, 用户ID: {}, 全名: {}, 简介: {:?}",
-                    profile.id, profile.user_id, profile.full_name, profile.bio);
+    match command {
+        Command::Demo => {
+            let run = run_demo(&pools).await?;
+            info!("演示流程共产生 {} 个事件", run.events.len());
+            for event in &run.events {
+                debug!("演示事件: {:?}", event);
             }
+            let summary = run.summary;
+            info!(
+                "演示汇总 - 创建用户: {}, 更新用户: {}, 删除用户: {}, 触发回滚: {}",
+                summary.users_created, summary.users_updated, summary.users_deleted, summary.rollbacks
+            );
         }
-        Err(e) => {
-            error!("多表事务创建失败: {}", e);
+        Command::Seed { count } => {
+            run_migrations(&pools).await?;
+            let ids = UserService::seed_users(&pools, count).await?;
+            info!("插入 {} 个随机用户", ids.len());
         }
-    }
-
-    // 9. 多表事务更新演示
-    if let Some(user) = crate::database::select_all_users(&pool).await?.first() {
-        if let Err(e) = UserProfileService::update_user_and_profile(&pool, user.id).await {
-            warn!("多表事务更新失败: {}", e);
+        Command::List => {
+            for user in select_all_users(&pools).await? {
+                println!("{}\t{}\t{}\t{}", user.id, user.username, user.email, user.created_at);
+            }
         }
+        Command::Migrate => {
+            run_migrations(&pools).await?;
+            info!("数据库迁移完成");
+        }
+        Command::Help => unreachable!(),
     }
 
-    // 10. 事务回滚测试 - 故意插入重复数据来演示回滚
-    if let Err(e) = UserProfileService::test_multi_table_transaction_rollback(&pool).await {
-        warn!("多表事务回滚测试失败: {}", e);
-    }
-
-    // 11. 最终验证 - 查询所有数据确认数据持久化
-    info!("最终验证 - 查询数据库中的所有用户:");
-    let final_users = select_all_users(&pool).await?;
-    info!("数据库中实际存在的用户数量: {}", final_users.len());
-    for user in &final_users {
-        info!(
-            "最终用户数据 - ID: {}, 用户名: {}, 邮箱: {}",
-            user.id, user.username, user.email
-        );
-    }
-
-    info!("最终验证 - 查询数据库中的所有 profiles:");
-    let final_profiles = crate::database::select_all_profiles(&pool).await?;
-    info!("数据库中实际存在的 profile 数量: {}", final_profiles.len());
-    for profile in &final_profiles {
-        info!(
-            "最终 Profile 数据 - ID: {}, 用户ID: {}, 全名: {}, 简介: {:?}",
-            profile.id, profile.user_id, profile.full_name, profile.bio
-        );
-    }
-
-    info!("SQLx MySQL 示例程序执行完成 - 所有事务操作（包括多表事务和回滚测试）已完成");
+    log_pool_status(&pools.primary);
     Ok(())
 }
 
@@ -130,3 +128,19 @@ async fn test_basic_operations() -> Result<()> {
     info!("测试结构演示");
     Ok(())
 }
+
+#[test]
+fn test_parse_args() {
+    let parse = |args: &[&str]| parse_args(args.iter().map(|arg| arg.to_string()));
+
+    assert_eq!(parse(&[]).unwrap(), Command::Demo);
+    assert_eq!(parse(&["--op", "list"]).unwrap(), Command::List);
+    assert_eq!(parse(&["--op", "seed"]).unwrap(), Command::Seed { count: DEFAULT_SEED_COUNT });
+    assert_eq!(parse(&["--op", "seed", "--count", "100"]).unwrap(), Command::Seed { count: 100 });
+    assert_eq!(parse(&["--help"]).unwrap(), Command::Help);
+
+    assert!(parse(&["--op", "drop"]).is_err());
+    assert!(parse(&["--op", "seed", "--count", "-1"]).is_err());
+    assert!(parse(&["--op", "list", "--count", "5"]).is_err());
+    assert!(parse(&["--verbose"]).is_err());
+}
@@ -0,0 +1,227 @@
+use anyhow::Result;
+use tracing::{debug, info};
+
+use crate::database::WritePool;
+use crate::error::AppError;
+
+// 按版本号顺序排列的迁移列表，已发布的迁移不要修改，新的变更追加在末尾
+pub const MIGRATIONS: &[(u32, &[MigrationStep])] = &[
+    (1, &[MigrationStep::always(V1_CREATE_USERS_SQL)]),
+    (2, &[MigrationStep::always(V2_CREATE_PROFILES_SQL)]),
+    (
+        3,
+        &[MigrationStep::unless(V3_ADD_PASSWORD_HASH_SQL, SchemaObject::Column("users", "password_hash"))],
+    ),
+    (
+        4,
+        &[
+            MigrationStep::unless(V4_ADD_DELETED_AT_SQL, SchemaObject::Column("users", "deleted_at")),
+            MigrationStep::unless(
+                V4_ADD_DELETED_AT_INDEX_SQL,
+                SchemaObject::Index("users", "idx_users_deleted_at"),
+            ),
+        ],
+    ),
+    (
+        5,
+        &[MigrationStep::unless(V5_ADD_IS_ACTIVE_SQL, SchemaObject::Column("users", "is_active"))],
+    ),
+];
+
+// 迁移要添加的表结构对象：(表名, 列名) 或 (表名, 索引名)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaObject {
+    Column(&'static str, &'static str),
+    Index(&'static str, &'static str),
+}
+
+// 迁移中的一条语句。MySQL 的 ALTER TABLE ... ADD COLUMN / ADD INDEX 不支持 IF NOT EXISTS，
+// 而 create_table() 等使用的最新建表语句已经包含后续迁移添加的列和索引，
+// 所以这类语句通过 skip_if_exists 先检查 information_schema，对象已存在时跳过，保持迁移幂等
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MigrationStep {
+    pub sql: &'static str,
+    pub skip_if_exists: Option<SchemaObject>,
+}
+
+impl MigrationStep {
+    // 总是执行的语句，本身应当幂等（如 CREATE TABLE IF NOT EXISTS）
+    pub const fn always(sql: &'static str) -> Self {
+        Self { sql, skip_if_exists: None }
+    }
+
+    // object 已存在时跳过的语句
+    pub const fn unless(sql: &'static str, object: SchemaObject) -> Self {
+        Self { sql, skip_if_exists: Some(object) }
+    }
+}
+
+// 版本 1：创建用户表
+const V1_CREATE_USERS_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS users (
+    id BIGINT UNSIGNED AUTO_INCREMENT PRIMARY KEY,
+    username VARCHAR(50) NOT NULL UNIQUE,
+    email VARCHAR(100) NOT NULL UNIQUE,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+"#;
+
+// 版本 2：创建 profile 表
+const V2_CREATE_PROFILES_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS profiles (
+    id BIGINT UNSIGNED AUTO_INCREMENT PRIMARY KEY,
+    user_id BIGINT UNSIGNED NOT NULL UNIQUE,
+    full_name VARCHAR(100) NOT NULL,
+    bio TEXT,
+    avatar_url VARCHAR(255),
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+"#;
+
+// 版本 3：用户表增加密码哈希列
+const V3_ADD_PASSWORD_HASH_SQL: &str = r#"
+ALTER TABLE users ADD COLUMN password_hash VARCHAR(255) AFTER email
+"#;
+
+// 版本 4：用户表增加软删除时间列，并为定期清理建立索引（列和索引分别检查是否已存在）
+const V4_ADD_DELETED_AT_SQL: &str = r#"
+ALTER TABLE users ADD COLUMN deleted_at TIMESTAMP NULL DEFAULT NULL
+"#;
+
+const V4_ADD_DELETED_AT_INDEX_SQL: &str = r#"
+ALTER TABLE users ADD INDEX idx_users_deleted_at (deleted_at)
+"#;
+
+// 版本 5：用户表增加启用状态列，已有用户默认为启用
+const V5_ADD_IS_ACTIVE_SQL: &str = r#"
+ALTER TABLE users ADD COLUMN is_active BOOLEAN NOT NULL DEFAULT TRUE AFTER password_hash
+"#;
+
+// 创建迁移记录表的SQL
+const CREATE_MIGRATIONS_TABLE_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS _migrations (
+    version INT UNSIGNED PRIMARY KEY,
+    applied_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+"#;
+
+// 查询已执行迁移版本的SQL
+const SELECT_APPLIED_MIGRATIONS_SQL: &str = r#"
+SELECT version FROM _migrations ORDER BY version
+"#;
+
+// 判断当前数据库中的表是否已有指定列的SQL
+const COLUMN_EXISTS_SQL: &str = r#"
+SELECT EXISTS(
+    SELECT 1 FROM information_schema.COLUMNS
+    WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = ? AND COLUMN_NAME = ?
+)
+"#;
+
+// 判断当前数据库中的表是否已有指定索引的SQL
+const INDEX_EXISTS_SQL: &str = r#"
+SELECT EXISTS(
+    SELECT 1 FROM information_schema.STATISTICS
+    WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = ? AND INDEX_NAME = ?
+)
+"#;
+
+// 记录迁移版本的SQL
+const INSERT_MIGRATION_SQL: &str = r#"
+INSERT INTO _migrations (version) VALUES (?)
+"#;
+
+// 按顺序执行尚未执行的迁移，每个迁移只执行一次
+//
+// MySQL 的 DDL 会隐式提交事务，无法与版本记录放在同一事务中，
+// 因此迁移语句应尽量保持幂等（如 CREATE TABLE IF NOT EXISTS，或通过 MigrationStep::unless 跳过已存在的列和索引），
+// 以便中途失败后可以重新执行，也可以在通过 create_all_tables() 建好的表上执行。
+// 迁移中的 DDL 是当时表结构的快照，不引用 models.rs 中的常量，避免后续修改影响已发布的迁移。
+// 大表上的 ALTER TABLE 可能执行很久，迁移语句不加查询超时，但错误同样转换为 AppError。
+// 所有语句（包括 _migrations 记录表）都按 pool.schema() 加上表名前缀，不同前缀的实例各自记录迁移版本。
+#[tracing::instrument]
+pub async fn run_migrations(pool: &impl WritePool) -> Result<()> {
+    info!("开始执行数据库迁移");
+    let schema = pool.schema();
+    let conn = pool.write_pool();
+    sqlx::query(&schema.sql(CREATE_MIGRATIONS_TABLE_SQL))
+        .execute(conn)
+        .await
+        .map_err(AppError::from)?;
+
+    let applied: Vec<u32> = sqlx::query_scalar(&schema.sql(SELECT_APPLIED_MIGRATIONS_SQL))
+        .fetch_all(conn)
+        .await
+        .map_err(AppError::from)?;
+    debug!("已执行的迁移版本: {:?}", applied);
+
+    let mut count = 0;
+    for (version, steps) in MIGRATIONS {
+        if applied.contains(version) {
+            continue;
+        }
+        info!("执行迁移 - 版本: {}", version);
+        for step in *steps {
+            if let Some(object) = step.skip_if_exists
+                && schema_object_exists(pool, object).await?
+            {
+                info!("迁移版本 {} 要添加的 {:?} 已存在，跳过", version, object);
+                continue;
+            }
+            sqlx::query(&schema.sql(step.sql))
+                .execute(conn)
+                .await
+                .map_err(AppError::from)?;
+        }
+        sqlx::query(&schema.sql(INSERT_MIGRATION_SQL))
+            .bind(version)
+            .execute(conn)
+            .await
+            .map_err(AppError::from)?;
+        count += 1;
+    }
+
+    info!("数据库迁移完成 - 本次执行 {} 个迁移", count);
+    Ok(())
+}
+
+// 查询 information_schema 判断列或索引是否已存在，表名按 pool.schema() 加上前缀
+async fn schema_object_exists(pool: &impl WritePool, object: SchemaObject) -> Result<bool> {
+    let (sql, table, name) = match object {
+        SchemaObject::Column(table, column) => (COLUMN_EXISTS_SQL, table, column),
+        SchemaObject::Index(table, index) => (INDEX_EXISTS_SQL, table, index),
+    };
+    let exists = sqlx::query_scalar::<_, bool>(sql)
+        .bind(pool.schema().table(table))
+        .bind(name)
+        .fetch_one(pool.write_pool())
+        .await
+        .map_err(AppError::from)?;
+    Ok(exists)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migration_versions_are_strictly_increasing() {
+        assert!(MIGRATIONS.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    }
+
+    #[test]
+    fn test_alter_table_steps_are_guarded() {
+        // ALTER TABLE 没有 IF NOT EXISTS，必须先检查对象是否存在才能重复执行
+        for (version, steps) in MIGRATIONS {
+            for step in *steps {
+                if step.sql.trim_start().starts_with("ALTER TABLE") {
+                    let guarded = step.skip_if_exists.is_some();
+                    assert!(guarded, "迁移版本 {} 的 ALTER TABLE 未加存在性检查", version);
+                }
+            }
+        }
+    }
+}
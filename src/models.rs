@@ -1,52 +1,373 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+// 用户表查询列，与 User 结构体字段一一对应
+// 使用宏以便在 concat! 中拼接出编译期常量SQL
+macro_rules! user_columns {
+    () => {
+        "id, username, email, is_active, created_at, updated_at"
+    };
+}
+
+// 用户表查询列
+pub const USER_COLUMNS: &str = user_columns!();
+
+// 健康检查的SQL
+pub const HEALTH_CHECK_SQL: &str = "SELECT 1";
+
 // 用户表结构
-#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+// 时间列假定为 UTC：sqlx 连接时把会话时区设为 +00:00，TIMESTAMP 由服务器按 UTC 返回后直接解码为
+// DateTime<Utc>，不会出现夏令时的一小时偏差；自行读取 NaiveDateTime 时用 utils::to_utc 转换
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct User {
     pub id: u64,
     pub username: String,
     pub email: String,
+    // 是否启用，停用的用户仍然存在并计入统计，可以重新启用（与软删除的 deleted_at 不同）
+    pub is_active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// 用户的另一种序列化视图：时间戳序列化为 Unix 时间戳（秒）而不是 RFC3339 字符串
+// 通过 UserEpoch::from(user) 转换后再序列化，User 本身的默认格式保持不变
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UserEpoch {
+    pub id: u64,
+    pub username: String,
+    pub email: String,
+    pub is_active: bool,
+    #[serde(with = "chrono::serde::ts_seconds")]
     pub created_at: DateTime<Utc>,
+    #[serde(with = "chrono::serde::ts_seconds")]
     pub updated_at: DateTime<Utc>,
 }
 
+impl From<User> for UserEpoch {
+    fn from(user: User) -> Self {
+        Self {
+            id: user.id,
+            username: user.username,
+            email: user.email,
+            is_active: user.is_active,
+            created_at: user.created_at,
+            updated_at: user.updated_at,
+        }
+    }
+}
+
+impl From<UserEpoch> for User {
+    fn from(user: UserEpoch) -> Self {
+        Self {
+            id: user.id,
+            username: user.username,
+            email: user.email,
+            is_active: user.is_active,
+            created_at: user.created_at,
+            updated_at: user.updated_at,
+        }
+    }
+}
+
+// 插入用户时由调用方提供的字段
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewUser {
+    pub username: String,
+    pub email: String,
+}
+
 // 创建用户表的SQL
 pub const CREATE_USER_TABLE_SQL: &str = r#"
 CREATE TABLE IF NOT EXISTS users (
     id BIGINT UNSIGNED AUTO_INCREMENT PRIMARY KEY,
     username VARCHAR(50) NOT NULL UNIQUE,
     email VARCHAR(100) NOT NULL UNIQUE,
+    password_hash VARCHAR(255),
+    is_active BOOLEAN NOT NULL DEFAULT TRUE,
     created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
-    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    deleted_at TIMESTAMP NULL DEFAULT NULL,
+    INDEX idx_users_deleted_at (deleted_at)
 ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
 "#;
 
+// 建表SQL常量中使用的默认排序规则
+const DEFAULT_TABLE_COLLATE: &str = "COLLATE=utf8mb4_unicode_ci";
+
+// 建表时使用的排序规则，字符集固定为 utf8mb4，默认与建表SQL常量中的 utf8mb4_unicode_ci 相同
+// 以 _ci 结尾的排序规则不区分大小写，username 的唯一约束会把 Alice 和 alice 视为重复，
+// 需要区分大小写的用户名时使用 Utf8mb4Bin
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Collation {
+    #[default]
+    Utf8mb4UnicodeCi,
+    // MySQL 8 的默认排序规则
+    Utf8mb40900AiCi,
+    // 按字节比较，区分大小写
+    Utf8mb4Bin,
+}
+
+impl Collation {
+    // 对应的 MySQL 排序规则名称
+    pub fn as_sql(self) -> &'static str {
+        match self {
+            Collation::Utf8mb4UnicodeCi => "utf8mb4_unicode_ci",
+            Collation::Utf8mb40900AiCi => "utf8mb4_0900_ai_ci",
+            Collation::Utf8mb4Bin => "utf8mb4_bin",
+        }
+    }
+
+    // 把建表SQL中的默认排序规则替换为当前排序规则，例如 Collation::Utf8mb4Bin.apply(CREATE_USER_TABLE_SQL)
+    // 只影响新建的表，已有的表需要另外执行 ALTER TABLE ... CONVERT TO CHARACTER SET
+    pub fn apply(self, ddl: &str) -> String {
+        ddl.replace(DEFAULT_TABLE_COLLATE, &format!("COLLATE={}", self.as_sql()))
+    }
+}
+
+// 生成创建用户表的SQL，use_datetime 为 false 时与 CREATE_USER_TABLE_SQL 相同
+// 时间列类型的取舍：
+// - TIMESTAMP：按 UTC 存储、读写时随会话时区转换，但取值范围只到 2038-01-19（2038 年问题）
+// - DATETIME：取值范围到 9999 年，按字面值存储不做时区转换，写入前需要自行统一为 UTC
+//   （sqlx 连接时会把会话时区设为 +00:00，通过本库读写不受影响）
+// 已有的表不会因此改变类型，切换时需要另外迁移
+pub fn user_table_ddl(use_datetime: bool) -> String {
+    let column_type = if use_datetime { "DATETIME" } else { "TIMESTAMP" };
+    format!(
+        r#"
+CREATE TABLE IF NOT EXISTS users (
+    id BIGINT UNSIGNED AUTO_INCREMENT PRIMARY KEY,
+    username VARCHAR(50) NOT NULL UNIQUE,
+    email VARCHAR(100) NOT NULL UNIQUE,
+    password_hash VARCHAR(255),
+    is_active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at {column_type} DEFAULT CURRENT_TIMESTAMP,
+    updated_at {column_type} DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    deleted_at {column_type} NULL DEFAULT NULL,
+    INDEX idx_users_deleted_at (deleted_at)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
+"#
+    )
+}
+
 // 插入用户的SQL
 pub const INSERT_USER_SQL: &str = r#"
 INSERT INTO users (username, email) VALUES (?, ?)
 "#;
 
+// 插入带密码哈希的用户的SQL
+pub const INSERT_USER_WITH_PASSWORD_SQL: &str = r#"
+INSERT INTO users (username, email, password_hash) VALUES (?, ?, ?)
+"#;
+
+// 根据用户名查询密码哈希的SQL
+pub const SELECT_PASSWORD_HASH_BY_USERNAME_SQL: &str = r#"
+SELECT password_hash FROM users WHERE username = ?
+"#;
+
+// 插入或更新用户的SQL（用户名冲突时更新邮箱）
+// id = LAST_INSERT_ID(id) 使更新时 last_insert_id() 也能返回已存在用户的 ID
+pub const INSERT_USER_UPSERT_SQL: &str = r#"
+INSERT INTO users (username, email) VALUES (?, ?)
+ON DUPLICATE KEY UPDATE id = LAST_INSERT_ID(id), email = VALUES(email)
+"#;
+
+// 根据用户名查询并锁定用户ID的SQL
+pub const SELECT_USER_ID_BY_USERNAME_FOR_UPDATE_SQL: &str = r#"
+SELECT id FROM users WHERE username = ? FOR UPDATE
+"#;
+
+// 根据邮箱查询并锁定用户ID的SQL（用于插入或更新前确认邮箱不属于其他用户）
+pub const SELECT_USER_ID_BY_EMAIL_FOR_UPDATE_SQL: &str = r#"
+SELECT id FROM users WHERE email = ? FOR UPDATE
+"#;
+
+// 根据ID查询并锁定用户的SQL
+pub const SELECT_USER_ID_FOR_UPDATE_SQL: &str = r#"
+SELECT id FROM users WHERE id = ? FOR UPDATE
+"#;
+
+// 批量插入用户的SQL前缀，需要按行数拼接 (?, ?) 占位符
+pub const INSERT_USERS_BATCH_SQL_PREFIX: &str = "INSERT INTO users (username, email) VALUES ";
+
+// 查询自增步长和自增锁模式的SQL
+pub const SELECT_AUTOINC_SETTINGS_SQL: &str = r#"
+SELECT CAST(@@auto_increment_increment AS SIGNED), CAST(@@innodb_autoinc_lock_mode AS SIGNED)
+"#;
+
 // 查询所有用户的SQL
-pub const SELECT_ALL_USERS_SQL: &str = r#"
-SELECT id, username, email, created_at, updated_at FROM users
+pub const SELECT_ALL_USERS_SQL: &str = concat!("SELECT ", user_columns!(), " FROM users");
+
+// 查询所有启用用户的SQL
+pub const SELECT_ACTIVE_USERS_SQL: &str =
+    concat!("SELECT ", user_columns!(), " FROM users WHERE is_active = TRUE");
+
+// 设置用户启用状态的SQL
+pub const SET_USER_ACTIVE_SQL: &str = r#"
+UPDATE users SET is_active = ? WHERE id = ?
+"#;
+
+// 统计用户数量的SQL
+pub const COUNT_USERS_SQL: &str = r#"
+SELECT COUNT(*) FROM users
+"#;
+
+// 统计使用指定邮箱的用户数量的SQL
+pub const COUNT_USERS_BY_EMAIL_SQL: &str = r#"
+SELECT COUNT(*) FROM users WHERE email = ?
 "#;
 
 // 根据ID查询用户的SQL
-pub const SELECT_USER_BY_ID_SQL: &str = r#"
-SELECT id, username, email, created_at, updated_at FROM users WHERE id = ?
+pub const SELECT_USER_BY_ID_SQL: &str =
+    concat!("SELECT ", user_columns!(), " FROM users WHERE id = ?");
+
+// 按ID列表查询用户的SQL前缀，需要在后面拼接 "(?, ?, ...)"
+pub const SELECT_USERS_BY_IDS_SQL_PREFIX: &str =
+    concat!("SELECT ", user_columns!(), " FROM users WHERE id IN ");
+
+// 根据用户名查询用户的SQL
+pub const SELECT_USER_BY_USERNAME_SQL: &str =
+    concat!("SELECT ", user_columns!(), " FROM users WHERE username = ?");
+
+// 按用户名精确（区分大小写）查询用户的SQL，需要绑定两次用户名
+// 第一个条件按列自身的排序规则比较，可以使用 username 的唯一索引；第二个条件用 utf8mb4_bin 按字节过滤
+pub const SELECT_USER_BY_USERNAME_EXACT_SQL: &str = concat!(
+    "SELECT ",
+    user_columns!(),
+    " FROM users WHERE username = ? AND username = ? COLLATE utf8mb4_bin"
+);
+
+// 根据邮箱查询用户的SQL
+pub const SELECT_USER_BY_EMAIL_SQL: &str =
+    concat!("SELECT ", user_columns!(), " FROM users WHERE email = ?");
+
+// 判断用户名是否已存在的SQL
+pub const USERNAME_EXISTS_SQL: &str = r#"
+SELECT EXISTS(SELECT 1 FROM users WHERE username = ?)
+"#;
+
+// 判断邮箱是否已存在的SQL
+pub const EMAIL_EXISTS_SQL: &str = r#"
+SELECT EXISTS(SELECT 1 FROM users WHERE email = ?)
+"#;
+
+// 判断用户名或邮箱是否已被占用的SQL（一次查询同时检查两者）
+pub const USERNAME_OR_EMAIL_EXISTS_SQL: &str = r#"
+SELECT EXISTS(SELECT 1 FROM users WHERE username = ? OR email = ?)
 "#;
 
+// 按用户名模糊搜索用户的SQL（参数需先转义 % 和 _）
+pub const SEARCH_USERS_BY_USERNAME_SQL: &str = concat!(
+    "SELECT ", user_columns!(), " FROM users ",
+    "WHERE username LIKE CONCAT('%', ?, '%') ORDER BY username LIMIT ?"
+);
+
+// 按 UserFilter 查询用户的SQL前缀，需要在后面拼接 UserFilter::where_clause() 和排序
+pub const SELECT_USERS_FILTERED_SQL_PREFIX: &str = concat!("SELECT ", user_columns!(), " FROM users");
+
+// 组合查询用户的条件，None 表示不按该字段过滤，设置的条件之间是 AND 关系
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UserFilter {
+    // 用户名包含该字符串（% 和 _ 按字面匹配）
+    pub username_contains: Option<String>,
+    // 邮箱 @ 之后的域名完全相等
+    pub email_domain: Option<String>,
+    // 创建时间晚于该时间（不含）
+    pub created_after: Option<DateTime<Utc>>,
+}
+
+impl UserFilter {
+    // 只包含已设置条件的 WHERE 子句，例如 " WHERE username LIKE ... AND created_at > ?"，
+    // 没有条件时返回空字符串；绑定顺序与字段声明顺序一致
+    pub fn where_clause(&self) -> String {
+        let mut conditions = Vec::new();
+        if self.username_contains.is_some() {
+            conditions.push("username LIKE CONCAT('%', ?, '%')");
+        }
+        if self.email_domain.is_some() {
+            conditions.push("SUBSTRING_INDEX(email, '@', -1) = ?");
+        }
+        if self.created_after.is_some() {
+            conditions.push("created_at > ?");
+        }
+        if conditions.is_empty() {
+            String::new()
+        } else {
+            format!(" WHERE {}", conditions.join(" AND "))
+        }
+    }
+}
+
+// 查询指定创建时间范围内用户的SQL
+pub const SELECT_USERS_CREATED_BETWEEN_SQL: &str = concat!(
+    "SELECT ", user_columns!(), " FROM users ",
+    "WHERE created_at BETWEEN ? AND ? ORDER BY created_at"
+);
+
+// 查询指定时间之后更新过的用户的SQL（按更新时间倒序，相同时按ID倒序）
+pub const SELECT_RECENTLY_UPDATED_USERS_SQL: &str = concat!(
+    "SELECT ", user_columns!(), " FROM users ",
+    "WHERE updated_at >= ? ORDER BY updated_at DESC, id DESC"
+);
+
+// 查询最近创建的 N 个用户的SQL（创建时间相同时按ID倒序）
+pub const SELECT_RECENT_USERS_SQL: &str = concat!(
+    "SELECT ", user_columns!(), " FROM users ",
+    "ORDER BY created_at DESC, id DESC LIMIT ?"
+);
+
 // 更新用户的SQL
 pub const UPDATE_USER_SQL: &str = r#"
 UPDATE users SET email = ? WHERE id = ?
 "#;
 
+// 仅当 updated_at 与调用方读取时一致才更新邮箱的SQL（乐观锁）
+pub const UPDATE_USER_EMAIL_IF_UNCHANGED_SQL: &str = r#"
+UPDATE users SET email = ? WHERE id = ? AND updated_at = ?
+"#;
+
+// 查找迁移邮箱域名后会与现有邮箱冲突的用户：(用户ID, 当前邮箱, 冲突用户ID)
+// 排除用户自身：默认排序规则不区分大小写，只改变大小写（或新旧域名相同）时每行都会与自己相等
+pub const SELECT_EMAIL_DOMAIN_CONFLICTS_SQL: &str = r#"
+SELECT u.id, u.email, o.id AS conflicting_id
+FROM users u
+JOIN users o ON o.email = CONCAT(SUBSTRING_INDEX(u.email, '@', 1), '@', ?) AND o.id <> u.id
+WHERE u.email LIKE CONCAT('%@', ?)
+"#;
+
+// 将邮箱域名整体替换为新域名的SQL（只改写 @ 之后的部分）
+pub const MIGRATE_EMAIL_DOMAIN_SQL: &str = r#"
+UPDATE users SET email = CONCAT(SUBSTRING_INDEX(email, '@', 1), '@', ?)
+WHERE email LIKE CONCAT('%@', ?)
+"#;
+
+// 更新用户名的SQL
+pub const UPDATE_USERNAME_SQL: &str = r#"
+UPDATE users SET username = ? WHERE id = ?
+"#;
+
 // 删除用户的SQL
 pub const DELETE_USER_SQL: &str = r#"
 DELETE FROM users WHERE id = ?
 "#;
 
+// 统计用户的 profile 数量并加锁的SQL，防止删除用户前有并发插入的 profile 未被计入
+pub const COUNT_PROFILES_BY_USER_ID_FOR_UPDATE_SQL: &str = r#"
+SELECT COUNT(*) FROM profiles WHERE user_id = ? FOR UPDATE
+"#;
+
+// 软删除用户的SQL（只记录删除时间，已软删除的用户不会重复标记）
+pub const SOFT_DELETE_USER_SQL: &str = r#"
+UPDATE users SET deleted_at = CURRENT_TIMESTAMP WHERE id = ? AND deleted_at IS NULL
+"#;
+
+// 物理删除软删除时间早于指定秒数之前的用户的SQL
+pub const PRUNE_SOFT_DELETED_USERS_SQL: &str = r#"
+DELETE FROM users WHERE deleted_at IS NOT NULL AND deleted_at < NOW() - INTERVAL ? SECOND
+"#;
+
+// 按ID列表批量删除用户的SQL前缀，需要拼接 (?, ?, ...) 占位符
+pub const DELETE_USERS_BY_IDS_SQL_PREFIX: &str = "DELETE FROM users WHERE id IN ";
+
 // Profile 表结构
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
 pub struct Profile {
@@ -59,6 +380,46 @@ pub struct Profile {
     pub updated_at: DateTime<Utc>,
 }
 
+// 插入 profile 时由调用方提供的字段
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewProfile {
+    pub user_id: u64,
+    pub full_name: String,
+    pub bio: Option<String>,
+    pub avatar_url: Option<String>,
+}
+
+// 部分更新 profile 时要修改的字段，None 表示不修改
+// bio 和 avatar_url 使用嵌套 Option：Some(None) 表示设为 NULL，Some(Some(..)) 表示设为新值
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateProfile {
+    pub full_name: Option<String>,
+    pub bio: Option<Option<String>>,
+    pub avatar_url: Option<Option<String>>,
+}
+
+impl UpdateProfile {
+    // 没有任何要修改的字段
+    pub fn is_empty(&self) -> bool {
+        self.full_name.is_none() && self.bio.is_none() && self.avatar_url.is_none()
+    }
+
+    // 只包含要修改字段的 SET 子句，例如 "bio = ?, avatar_url = ?"，绑定顺序与字段声明顺序一致
+    pub fn set_clause(&self) -> String {
+        let mut columns = Vec::new();
+        if self.full_name.is_some() {
+            columns.push("full_name = ?");
+        }
+        if self.bio.is_some() {
+            columns.push("bio = ?");
+        }
+        if self.avatar_url.is_some() {
+            columns.push("avatar_url = ?");
+        }
+        columns.join(", ")
+    }
+}
+
 // 创建 profile 表的SQL
 pub const CREATE_PROFILE_TABLE_SQL: &str = r#"
 CREATE TABLE IF NOT EXISTS profiles (
@@ -73,16 +434,50 @@ CREATE TABLE IF NOT EXISTS profiles (
 ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
 "#;
 
+// 导出所有建表语句，按依赖顺序排列（profiles 的外键引用 users，所以 users 在前），
+// 每条语句前加一行注释作为分隔，输出可直接保存为 .sql 文件交给 DBA 执行。不访问数据库
+pub fn schema_ddl() -> String {
+    [("users", CREATE_USER_TABLE_SQL), ("profiles", CREATE_PROFILE_TABLE_SQL)]
+        .iter()
+        .map(|(table, sql)| format!("-- 表: {}\n{}\n", table, sql.trim()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 // 插入 profile 的SQL
 pub const INSERT_PROFILE_SQL: &str = r#"
 INSERT INTO profiles (user_id, full_name, bio, avatar_url) VALUES (?, ?, ?, ?)
 "#;
 
+// 插入或更新 profile 的SQL（user_id 唯一，已存在时更新其余字段）
+// id = LAST_INSERT_ID(id) 使更新时 last_insert_id() 也能返回已存在 profile 的 ID
+pub const UPSERT_PROFILE_SQL: &str = r#"
+INSERT INTO profiles (user_id, full_name, bio, avatar_url) VALUES (?, ?, ?, ?)
+ON DUPLICATE KEY UPDATE id = LAST_INSERT_ID(id), full_name = VALUES(full_name),
+    bio = VALUES(bio), avatar_url = VALUES(avatar_url)
+"#;
+
 // 查询所有 profiles 的SQL
 pub const SELECT_ALL_PROFILES_SQL: &str = r#"
 SELECT id, user_id, full_name, bio, avatar_url, created_at, updated_at FROM profiles
 "#;
 
+// 统计 profile 数量的SQL
+pub const COUNT_PROFILES_SQL: &str = r#"
+SELECT COUNT(*) FROM profiles
+"#;
+
+// 根据ID查询 profile 的SQL
+pub const SELECT_PROFILE_BY_ID_SQL: &str = r#"
+SELECT id, user_id, full_name, bio, avatar_url, created_at, updated_at FROM profiles WHERE id = ?
+"#;
+
+// 按姓名模糊搜索 profile 的SQL（参数需先转义 % 和 _）
+pub const SEARCH_PROFILES_BY_NAME_SQL: &str = r#"
+SELECT id, user_id, full_name, bio, avatar_url, created_at, updated_at FROM profiles
+WHERE full_name LIKE CONCAT('%', ?, '%') ORDER BY full_name LIMIT ?
+"#;
+
 // 根据 user_id 查询 profile 的SQL
 pub const SELECT_PROFILE_BY_USER_ID_SQL: &str = r#"
 SELECT id, user_id, full_name, bio, avatar_url, created_at, updated_at FROM profiles WHERE user_id = ?
@@ -93,7 +488,231 @@ pub const UPDATE_PROFILE_SQL: &str = r#"
 UPDATE profiles SET full_name = ?, bio = ?, avatar_url = ? WHERE user_id = ?
 "#;
 
+// 部分更新 profile 的SQL前缀，需要在后面拼接 SET 子句和 " WHERE user_id = ?"
+pub const UPDATE_PROFILE_FIELDS_SQL_PREFIX: &str = "UPDATE profiles SET ";
+
+// 将 profile 转移给另一个用户的SQL
+pub const REASSIGN_PROFILE_SQL: &str = r#"
+UPDATE profiles SET user_id = ? WHERE user_id = ?
+"#;
+
 // 删除 profile 的SQL
 pub const DELETE_PROFILE_SQL: &str = r#"
 DELETE FROM profiles WHERE user_id = ?
-"#;
\ No newline at end of file
+"#;
+// 查找拥有多个 profile 的用户的SQL（user_id 有唯一约束，正常情况下结果为空）
+pub const FIND_DUPLICATE_PROFILES_SQL: &str = r#"
+SELECT user_id, COUNT(*) FROM profiles GROUP BY user_id HAVING COUNT(*) > 1 ORDER BY user_id
+"#;
+// 查找 user_id 指向不存在用户的孤立 profile 的SQL（外键正常时结果为空）
+pub const FIND_ORPHANED_PROFILES_SQL: &str = r#"
+SELECT p.id, p.user_id, p.full_name, p.bio, p.avatar_url, p.created_at, p.updated_at
+FROM profiles p
+LEFT JOIN users u ON u.id = p.user_id
+WHERE u.id IS NULL
+ORDER BY p.id
+"#;
+
+// 用户及其 profile 的联合查询结果（没有 profile 的用户对应字段为 None）
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct UserWithProfile {
+    pub id: u64,
+    pub username: String,
+    pub email: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub profile_id: Option<u64>,
+    pub full_name: Option<String>,
+    pub bio: Option<String>,
+    pub avatar_url: Option<String>,
+}
+
+// 查询所有用户及其 profile 的SQL
+pub const SELECT_USERS_WITH_PROFILES_SQL: &str = r#"
+SELECT u.id, u.username, u.email, u.created_at, u.updated_at,
+       p.id AS profile_id, p.full_name, p.bio, p.avatar_url
+FROM users u
+LEFT JOIN profiles p ON u.id = p.user_id
+ORDER BY u.id
+"#;
+
+// 根据ID查询用户及其完整 profile 的SQL（profile 列加前缀避免与用户列重名）
+pub const SELECT_USER_WITH_PROFILE_BY_ID_SQL: &str = r#"
+SELECT u.id, u.username, u.email, u.is_active, u.created_at, u.updated_at,
+       p.id AS profile_id, p.user_id AS profile_user_id, p.full_name, p.bio, p.avatar_url,
+       p.created_at AS profile_created_at, p.updated_at AS profile_updated_at
+FROM users u
+LEFT JOIN profiles p ON u.id = p.user_id
+WHERE u.id = ?
+"#;
+
+// 查询没有 profile 的用户的SQL
+pub const SELECT_USERS_WITHOUT_PROFILE_SQL: &str = r#"
+SELECT u.id, u.username, u.email, u.is_active, u.created_at, u.updated_at
+FROM users u
+LEFT JOIN profiles p ON u.id = p.user_id
+WHERE p.id IS NULL
+ORDER BY u.id
+"#;
+
+// 级联删除用户的结果：用户是否被删除，以及外键 ON DELETE CASCADE 一并删除的 profile 数量
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeleteReport {
+    pub user_deleted: bool,
+    pub profiles_deleted: u64,
+}
+
+// 同时更新用户和 profile 的结果：两条 UPDATE 各自匹配到的行数
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UpdateReport {
+    pub user_rows: u64,
+    pub profile_rows: u64,
+}
+
+// 分页接口返回的数据封装，序列化为 { "data": [...], "total": N, "limit": L, "offset": O }
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Page<T> {
+    pub data: Vec<T>,
+    pub total: i64,
+    pub limit: u64,
+    pub offset: u64,
+}
+
+// 状态页使用的汇总统计
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Stats {
+    pub total_users: i64,
+    pub total_profiles: i64,
+    pub users_created_today: i64,
+}
+
+// 统计今天创建的用户数量的SQL（按会话时区计算"今天"，sqlx 连接默认为 UTC）
+pub const COUNT_USERS_CREATED_TODAY_SQL: &str = r#"
+SELECT COUNT(*) FROM users WHERE created_at >= CURDATE()
+"#;
+
+// 按邮箱域名统计的用户数量
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct DomainCount {
+    pub domain: String,
+    pub count: i64,
+}
+
+// 按邮箱域名分组统计用户数量的SQL（数量相同时按域名排序，保证结果稳定）
+pub const COUNT_USERS_BY_DOMAIN_SQL: &str = r#"
+SELECT SUBSTRING_INDEX(email, '@', -1) AS domain, COUNT(*) AS count
+FROM users
+GROUP BY domain
+ORDER BY count DESC, domain
+"#;
+
+// 查询所有不重复的邮箱域名的SQL，按域名字母顺序排序
+pub const SELECT_DISTINCT_EMAIL_DOMAINS_SQL: &str = r#"
+SELECT DISTINCT SUBSTRING_INDEX(email, '@', -1) FROM users ORDER BY 1
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_user_table_ddl() {
+        assert_eq!(user_table_ddl(false), CREATE_USER_TABLE_SQL);
+
+        let ddl = user_table_ddl(true);
+        assert!(ddl.contains("created_at DATETIME DEFAULT CURRENT_TIMESTAMP"));
+        assert!(ddl.contains("updated_at DATETIME DEFAULT CURRENT_TIMESTAMP ON UPDATE"));
+        assert!(!ddl.contains("TIMESTAMP DEFAULT"));
+    }
+
+    #[test]
+    fn test_schema_ddl_orders_tables_by_dependency() {
+        let ddl = schema_ddl();
+        let users = ddl.find("CREATE TABLE IF NOT EXISTS users").unwrap();
+        let profiles = ddl.find("CREATE TABLE IF NOT EXISTS profiles").unwrap();
+        assert!(users < profiles);
+        assert!(ddl.starts_with("-- 表: users\n"));
+        assert!(ddl.contains(";\n\n-- 表: profiles\n"));
+        assert!(ddl.ends_with(";\n"));
+    }
+
+    #[test]
+    fn test_collation_apply() {
+        assert_eq!(Collation::default().apply(CREATE_USER_TABLE_SQL), CREATE_USER_TABLE_SQL);
+
+        let ddl = Collation::Utf8mb4Bin.apply(CREATE_PROFILE_TABLE_SQL);
+        assert!(ddl.contains("DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_bin;"));
+        assert!(!ddl.contains("utf8mb4_unicode_ci"));
+
+        let ddl = Collation::Utf8mb40900AiCi.apply(&user_table_ddl(true));
+        assert!(ddl.contains("COLLATE=utf8mb4_0900_ai_ci"));
+    }
+
+    #[test]
+    fn test_user_filter_where_clause() {
+        assert_eq!(UserFilter::default().where_clause(), "");
+
+        let filter = UserFilter {
+            username_contains: Some("al".to_string()),
+            created_after: Some(Utc::now()),
+            ..Default::default()
+        };
+        assert_eq!(
+            filter.where_clause(),
+            " WHERE username LIKE CONCAT('%', ?, '%') AND created_at > ?"
+        );
+    }
+
+    #[test]
+    fn test_update_profile_set_clause() {
+        let update = UpdateProfile::default();
+        assert!(update.is_empty());
+        assert_eq!(update.set_clause(), "");
+
+        let update = UpdateProfile {
+            bio: Some(Some("新的简介".to_string())),
+            avatar_url: Some(None),
+            ..Default::default()
+        };
+        assert!(!update.is_empty());
+        assert_eq!(update.set_clause(), "bio = ?, avatar_url = ?");
+    }
+
+    #[test]
+    fn test_page_envelope() {
+        let page = Page { data: vec!["a", "b"], total: 5, limit: 2, offset: 2 };
+        let json = serde_json::to_value(&page).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "data": ["a", "b"], "total": 5, "limit": 2, "offset": 2 })
+        );
+    }
+
+    #[test]
+    fn test_user_epoch_round_trip() {
+        use chrono::TimeZone;
+
+        let created_at = Utc.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap();
+        let user = User {
+            id: 7,
+            username: "alice".to_string(),
+            email: "alice@example.com".to_string(),
+            is_active: true,
+            created_at,
+            updated_at: created_at + chrono::Duration::seconds(60),
+        };
+
+        let json = serde_json::to_value(UserEpoch::from(user.clone())).unwrap();
+        assert_eq!(json["created_at"], created_at.timestamp());
+        assert_eq!(json["updated_at"], created_at.timestamp() + 60);
+
+        let back = User::from(serde_json::from_value::<UserEpoch>(json).unwrap());
+        assert_eq!(back.created_at, user.created_at);
+        assert_eq!(back.updated_at, user.updated_at);
+        assert_eq!(back.username, user.username);
+
+        // User 本身仍序列化为 RFC3339
+        let default_json = serde_json::to_value(&user).unwrap();
+        assert_eq!(default_json["created_at"], "2024-01-02T03:04:05Z");
+    }
+}
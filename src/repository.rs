@@ -0,0 +1,116 @@
+use std::future::Future;
+
+use anyhow::Result;
+
+use crate::database::{
+    WritePool, select_all_profiles, select_all_users, select_profile_by_id, select_user_by_id,
+};
+use crate::error::AppError;
+use crate::models::{NewProfile, NewUser, Profile, User};
+use crate::services::{ProfileService, UserService};
+
+// 用户和 profile 共用的增删查接口，泛型代码可以同时处理两种实体
+// 返回的 future 都是 Send，可以直接交给 tokio::spawn 等要求 Send 的执行器
+// 新增表时实现这个 trait 即可，具体实现委托给 database.rs / services.rs 中已有的函数
+// 传入 AppPools 时查询走只读副本，写入后的回读走主库，表名前缀同样生效
+pub trait Repository: Sized {
+    // 插入时由调用方提供的字段
+    type New;
+
+    // 插入记录并返回包含ID和时间戳的完整记录
+    fn insert(pool: &impl WritePool, new: &Self::New) -> impl Future<Output = Result<Self>> + Send;
+
+    // 根据ID查询记录
+    fn find_by_id(pool: &impl WritePool, id: u64) -> impl Future<Output = Result<Option<Self>>> + Send;
+
+    // 查询所有记录
+    fn all(pool: &impl WritePool) -> impl Future<Output = Result<Vec<Self>>> + Send;
+
+    // 根据ID删除记录，记录不存在时返回 AppError::NotFound
+    fn delete(pool: &impl WritePool, id: u64) -> impl Future<Output = Result<()>> + Send;
+}
+
+impl Repository for User {
+    type New = NewUser;
+
+    async fn insert(pool: &impl WritePool, new: &Self::New) -> Result<User> {
+        UserService::insert_user_returning(pool, &new.username, &new.email).await
+    }
+
+    async fn find_by_id(pool: &impl WritePool, id: u64) -> Result<Option<User>> {
+        select_user_by_id(pool, id).await
+    }
+
+    async fn all(pool: &impl WritePool) -> Result<Vec<User>> {
+        select_all_users(pool).await
+    }
+
+    async fn delete(pool: &impl WritePool, id: u64) -> Result<()> {
+        match UserService::delete_users_by_ids(pool, &[id]).await? {
+            0 => Err(AppError::NotFound(format!("ID为 {} 的用户", id)).into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Repository for Profile {
+    type New = NewProfile;
+
+    async fn insert(pool: &impl WritePool, new: &Self::New) -> Result<Profile> {
+        let profile_id = ProfileService::insert_profile(
+            pool,
+            new.user_id,
+            &new.full_name,
+            new.bio.as_deref(),
+            new.avatar_url.as_deref(),
+        )
+        .await?;
+        select_profile_by_id(&pool.read_primary(), profile_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("ID为 {} 的 profile", profile_id)).into())
+    }
+
+    async fn find_by_id(pool: &impl WritePool, id: u64) -> Result<Option<Profile>> {
+        select_profile_by_id(pool, id).await
+    }
+
+    async fn all(pool: &impl WritePool) -> Result<Vec<Profile>> {
+        select_all_profiles(pool).await
+    }
+
+    // profiles.user_id 唯一，按 user_id 删除即删除这一条 profile
+    async fn delete(pool: &impl WritePool, id: u64) -> Result<()> {
+        let profile = select_profile_by_id(&pool.read_primary(), id)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("ID为 {} 的 profile", id)))?;
+        ProfileService::delete_profile(pool, profile.user_id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::mysql::MySqlPoolOptions;
+
+    use super::*;
+    use crate::database::AppPools;
+
+    fn assert_send<F: Future + Send>(_: F) {}
+
+    #[tokio::test]
+    async fn test_repository_futures_are_send() {
+        let pool = MySqlPoolOptions::new().connect_lazy("mysql://localhost/unused").unwrap();
+        let pools = AppPools::single(pool);
+        let new_user = NewUser { username: "alice".to_string(), email: "alice@example.com".to_string() };
+        let new_profile = NewProfile { user_id: 1, full_name: "Alice".to_string(), bio: None, avatar_url: None };
+
+        // 只构造 future 检查 Send，不会执行查询
+        assert_send(User::insert(&pools, &new_user));
+        assert_send(User::find_by_id(&pools, 1));
+        assert_send(User::all(&pools));
+        assert_send(User::delete(&pools, 1));
+        assert_send(Profile::insert(&pools, &new_profile));
+        assert_send(Profile::find_by_id(&pools, 1));
+        assert_send(Profile::all(&pools));
+        assert_send(Profile::delete(&pools, 1));
+    }
+}
@@ -0,0 +1,159 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+use anyhow::Result;
+
+use crate::error::AppError;
+use crate::models::{CREATE_PROFILE_TABLE_SQL, CREATE_USER_TABLE_SQL};
+
+// 本库使用的表名（含迁移记录表），Schema 会为 SQL 中出现的这些表名加上前缀
+const TABLE_NAMES: &[&str] = &["users", "profiles", "_migrations"];
+
+// 表名前缀配置，用于在同一个数据库中运行多个互相隔离的实例（例如 test_users）
+// 默认前缀为空，生成的SQL与 models.rs 中的常量完全相同
+//
+// Schema 随连接池一起传递：AppPools::schema 对通过它执行的建表、迁移、查询和服务层写入都生效，
+// 直接传入 Pool<MySql> 时使用默认的空前缀
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Schema {
+    pub prefix: String,
+}
+
+impl Schema {
+    // 前缀会直接拼接到SQL中，只允许字母、数字和下划线
+    pub fn new(prefix: impl Into<String>) -> Result<Self> {
+        let prefix = prefix.into();
+        if !prefix.chars().all(is_identifier_char) {
+            return Err(AppError::Validation(format!(
+                "表名前缀只能包含字母、数字和下划线: {:?}",
+                prefix
+            ))
+            .into());
+        }
+        Ok(Self { prefix })
+    }
+
+    // 从环境变量 DB_TABLE_PREFIX 读取前缀，未设置时前缀为空
+    pub fn from_env() -> Result<Self> {
+        match std::env::var("DB_TABLE_PREFIX") {
+            Ok(prefix) => Self::new(prefix),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    // 加上前缀后的表名，例如 schema.table("users")
+    pub fn table(&self, name: &str) -> String {
+        format!("{}{}", self.prefix, name)
+    }
+
+    // 加上前缀后的用户表名
+    pub fn users_table(&self) -> String {
+        self.table("users")
+    }
+
+    // 加上前缀后的 profile 表名
+    pub fn profiles_table(&self) -> String {
+        self.table("profiles")
+    }
+
+    // 创建用户表的SQL
+    pub fn create_user_table_sql(&self) -> String {
+        self.sql(CREATE_USER_TABLE_SQL).into_owned()
+    }
+
+    // 创建 profile 表的SQL（外键同样指向加前缀后的用户表）
+    pub fn create_profile_table_sql(&self) -> String {
+        self.sql(CREATE_PROFILE_TABLE_SQL).into_owned()
+    }
+
+    // 将 models.rs 中的任意SQL常量改写为使用带前缀的表名，例如 schema.sql(SELECT_ALL_USERS_SQL)
+    // 只替换完整的标识符，user_id、total_users 等列名和别名不受影响；前缀为空时直接借用原SQL，不分配内存
+    pub fn sql<'a>(&self, sql: &'a str) -> Cow<'a, str> {
+        if self.prefix.is_empty() {
+            return Cow::Borrowed(sql);
+        }
+
+        let mut output = String::with_capacity(sql.len() + self.prefix.len() * 4);
+        let mut rest = sql;
+        while let Some(start) = rest.find(is_identifier_char) {
+            output.push_str(&rest[..start]);
+            rest = &rest[start..];
+            let end = rest.find(|c: char| !is_identifier_char(c)).unwrap_or(rest.len());
+            let identifier = &rest[..end];
+            if TABLE_NAMES.contains(&identifier) {
+                output.push_str(&self.prefix);
+            }
+            output.push_str(identifier);
+            rest = &rest[end..];
+        }
+        output.push_str(rest);
+        Cow::Owned(output)
+    }
+
+    // 与 sql() 相同，但返回 'static 的SQL，用于 stream_all_users() 这类返回值借用SQL字符串的场景
+    // 每个 (前缀, SQL常量) 组合只改写一次并常驻内存，前缀和常量的数量都很少，占用的内存有上限
+    pub fn static_sql(&self, sql: &'static str) -> &'static str {
+        if self.prefix.is_empty() {
+            return sql;
+        }
+
+        type Cache = Mutex<HashMap<(String, &'static str), &'static str>>;
+        static CACHE: OnceLock<Cache> = OnceLock::new();
+        let mut cache = CACHE
+            .get_or_init(Default::default)
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        cache
+            .entry((self.prefix.clone(), sql))
+            .or_insert_with(|| Box::leak(self.sql(sql).into_owned().into_boxed_str()))
+    }
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{SELECT_USERS_WITH_PROFILES_SQL, USERNAME_EXISTS_SQL};
+
+    #[test]
+    fn test_default_schema_keeps_sql_unchanged() {
+        let schema = Schema::default();
+        assert_eq!(schema.create_user_table_sql(), CREATE_USER_TABLE_SQL);
+        assert_eq!(schema.sql(SELECT_USERS_WITH_PROFILES_SQL), SELECT_USERS_WITH_PROFILES_SQL);
+    }
+
+    #[test]
+    fn test_schema_prefixes_table_names_only() {
+        let schema = Schema::new("test_").unwrap();
+        assert_eq!(schema.users_table(), "test_users");
+        assert_eq!(schema.sql("SELECT version FROM _migrations"), "SELECT version FROM test__migrations");
+        assert_eq!(
+            schema.sql(USERNAME_EXISTS_SQL).trim(),
+            "SELECT EXISTS(SELECT 1 FROM test_users WHERE username = ?)"
+        );
+
+        let ddl = schema.create_profile_table_sql();
+        assert!(ddl.contains("CREATE TABLE IF NOT EXISTS test_profiles"));
+        assert!(ddl.contains("REFERENCES test_users(id)"));
+        assert!(ddl.contains("user_id BIGINT UNSIGNED"));
+
+        let join = schema.sql(SELECT_USERS_WITH_PROFILES_SQL);
+        assert!(join.contains("FROM test_users u"));
+        assert!(join.contains("LEFT JOIN test_profiles p ON u.id = p.user_id"));
+
+        let cached = schema.static_sql(USERNAME_EXISTS_SQL);
+        assert_eq!(cached, schema.sql(USERNAME_EXISTS_SQL));
+        assert!(std::ptr::eq(cached, schema.static_sql(USERNAME_EXISTS_SQL)));
+    }
+
+    #[test]
+    fn test_schema_rejects_invalid_prefix() {
+        assert!(Schema::new("app_1_").is_ok());
+        let err = Schema::new("x; DROP TABLE users; --").unwrap_err();
+        assert!(matches!(err.downcast_ref::<AppError>(), Some(AppError::Validation(_))));
+    }
+}
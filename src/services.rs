@@ -1,26 +1,80 @@
 use anyhow::Result;
-use sqlx::{MySql, Pool};
-use tracing::{error, info, warn};
+use chrono::{DateTime, Utc};
+use std::fmt::Display;
+use sqlx::{Executor, MySqlConnection, Statement};
+use tracing::{debug, error, info, warn};
 
 use crate::models::{
-    DELETE_PROFILE_SQL, DELETE_USER_SQL, INSERT_PROFILE_SQL, INSERT_USER_SQL,
-    UPDATE_PROFILE_SQL, UPDATE_USER_SQL
+    COUNT_PROFILES_BY_USER_ID_FOR_UPDATE_SQL, DELETE_PROFILE_SQL, DELETE_USER_SQL,
+    DELETE_USERS_BY_IDS_SQL_PREFIX, DeleteReport, INSERT_PROFILE_SQL, INSERT_USER_SQL,
+    INSERT_USER_UPSERT_SQL, INSERT_USER_WITH_PASSWORD_SQL, INSERT_USERS_BATCH_SQL_PREFIX,
+    MIGRATE_EMAIL_DOMAIN_SQL, NewUser, Profile, REASSIGN_PROFILE_SQL,
+    SELECT_EMAIL_DOMAIN_CONFLICTS_SQL, SELECT_PASSWORD_HASH_BY_USERNAME_SQL,
+    SELECT_PROFILE_BY_USER_ID_SQL, SELECT_USER_BY_EMAIL_SQL, SELECT_USER_BY_ID_SQL,
+    SELECT_USER_ID_BY_EMAIL_FOR_UPDATE_SQL, SELECT_USER_ID_BY_USERNAME_FOR_UPDATE_SQL,
+    SELECT_USER_ID_FOR_UPDATE_SQL, SET_USER_ACTIVE_SQL, SOFT_DELETE_USER_SQL,
+    UPDATE_PROFILE_FIELDS_SQL_PREFIX, UPDATE_PROFILE_SQL, UPDATE_USER_EMAIL_IF_UNCHANGED_SQL,
+    UPDATE_USER_SQL, UPDATE_USERNAME_SQL, UpdateProfile, UpdateReport, UPSERT_PROFILE_SQL, User,
 };
-use crate::utils::{generate_random_email, generate_random_username};
+use crate::database::{
+    IsolationLevel, WritePool, begin_logged, begin_with_isolation, retry_on_deadlock, with_transaction,
+};
+use crate::error::{AppError, is_duplicate_key, is_duplicate_key_error};
+use crate::schema::Schema;
+use crate::utils::{
+    escape_like, format_bind_params, generate_default_username, generate_random_email_default,
+    hash_password, last_id_for_batch, validate_bio, validate_email, validate_url,
+    verify_password_hash,
+};
+
+// 填充随机用户时每条多行 INSERT 包含的行数
+const SEED_CHUNK_SIZE: usize = 100;
+
+// 同时创建用户和 profile 遇到死锁时的最大重试次数
+const CREATE_USER_WITH_PROFILE_RETRIES: usize = 3;
+
+// 执行前在 debug 级别记录绑定的参数，需要启用 log_bind feature，密码哈希等敏感值会被隐藏
+fn log_bind(statement: &str, params: &[(&str, &dyn Display)]) {
+    if cfg!(feature = "log_bind") {
+        debug!("绑定参数 - {}: {}", statement, format_bind_params(params));
+    }
+}
+
+// 执行一条多行 INSERT 插入用户，返回按 "第一行 ID + 行偏移量" 计算出的所有 ID
+async fn insert_users_rows(
+    conn: &mut MySqlConnection,
+    schema: &Schema,
+    users: &[(String, String)],
+) -> std::result::Result<Vec<u64>, sqlx::Error> {
+    let placeholders = vec!["(?, ?)"; users.len()].join(", ");
+    let sql = format!("{}{}", INSERT_USERS_BATCH_SQL_PREFIX, placeholders);
+    let sql = schema.sql(&sql);
+
+    let mut query = sqlx::query(&sql);
+    for (username, email) in users {
+        query = query.bind(username).bind(email);
+    }
+
+    // last_insert_id() 取自执行这条 INSERT 的同一连接，不受其他连接的并发插入影响
+    let first_id = query.execute(conn).await?.last_insert_id();
+    Ok(last_id_for_batch(first_id, users.len()).collect())
+}
 
 // 用户服务
 pub struct UserService;
 
 impl UserService {
     // 插入用户（使用事务确保提交，失败时回滚）
-    pub async fn insert_user(pool: &Pool<MySql>) -> Result<u64> {
-        let mut transaction = pool.begin().await?;
+    pub async fn insert_user(pool: &impl WritePool) -> Result<u64> {
+        let username = generate_default_username();
+        let email = generate_random_email_default();
+        validate_email(&email)?;
+
+        let mut transaction = begin_logged(pool.write_pool()).await?;
         info!("开始事务插入用户");
         
-        let username = generate_random_username();
-        let email = generate_random_email();
-        
-        match sqlx::query(INSERT_USER_SQL)
+        log_bind("INSERT_USER_SQL", &[("username", &username), ("email", &email)]);
+        match sqlx::query(&pool.schema().sql(INSERT_USER_SQL))
             .bind(&username)
             .bind(&email)
             .execute(&mut *transaction)
@@ -40,63 +94,619 @@ impl UserService {
                 error!("插入用户失败: {}", e);
                 transaction.rollback().await?;
                 error!("事务已回滚");
-                Err(e.into())
+                Err(AppError::from(e).into())
             }
         }
     }
 
-    // 更新用户邮箱（使用事务确保提交，失败时回滚）
-    pub async fn update_user_email(pool: &Pool<MySql>, user_id: u64) -> Result<()> {
-        if let Some(user) = crate::database::select_user_by_id(pool, user_id).await? {
-            let new_email = format!("updated_{}", user.email);
-            
-            let mut transaction = pool.begin().await?;
-            info!("开始事务更新用户邮箱");
-            
-            match sqlx::query(UPDATE_USER_SQL)
-                .bind(&new_email)
+    // 使用调用方提供的用户名和邮箱插入用户（使用事务确保提交，失败时回滚），返回新用户的 ID
+    pub async fn insert_new_user(pool: &impl WritePool, new: NewUser) -> Result<u64> {
+        if new.username.trim().is_empty() {
+            return Err(AppError::Validation("用户名不能为空".to_string()).into());
+        }
+        validate_email(&new.email)?;
+
+        info!("开始事务插入用户 - 用户名: {}", new.username);
+        let user_id = with_transaction(pool.write_pool(), async |transaction| {
+            log_bind("INSERT_USER_SQL", &[("username", &new.username), ("email", &new.email)]);
+            let result = sqlx::query(&pool.schema().sql(INSERT_USER_SQL))
+                .bind(&new.username)
+                .bind(&new.email)
+                .execute(&mut **transaction)
+                .await?;
+            Ok(result.last_insert_id())
+        })
+        .await?;
+
+        info!("事务提交成功 - 插入用户 ID: {}", user_id);
+        Ok(user_id)
+    }
+
+    // 插入用户并在同一事务中查询新行，返回包含服务端时间戳的完整用户（使用事务确保提交，失败时回滚）
+    pub async fn insert_user_returning(pool: &impl WritePool, username: &str, email: &str) -> Result<User> {
+        validate_email(email)?;
+
+        info!("开始事务插入用户并返回完整记录");
+        let user = with_transaction(pool.write_pool(), async |transaction| {
+            log_bind("INSERT_USER_SQL", &[("username", &username), ("email", &email)]);
+            let user_id = sqlx::query(&pool.schema().sql(INSERT_USER_SQL))
+                .bind(username)
+                .bind(email)
+                .execute(&mut **transaction)
+                .await?
+                .last_insert_id();
+            info!("事务中插入用户成功 - ID: {}", user_id);
+
+            let user = sqlx::query_as::<_, User>(&pool.schema().sql(SELECT_USER_BY_ID_SQL))
                 .bind(user_id)
-                .execute(&mut *transaction)
+                .fetch_one(&mut **transaction)
+                .await?;
+            Ok(user)
+        })
+        .await?;
+
+        info!("事务提交成功 - 用户 ID: {}, 创建时间: {}", user.id, user.created_at);
+        Ok(user)
+    }
+
+    // 注册用户（密码经 Argon2 哈希后存储，从不保存明文，使用事务确保提交，失败时回滚）
+    pub async fn register_user(pool: &impl WritePool, username: &str, email: &str, password: &str) -> Result<u64> {
+        validate_email(email)?;
+        if password.is_empty() {
+            return Err(AppError::Validation("密码不能为空".to_string()).into());
+        }
+
+        // 哈希计算较耗 CPU，放到阻塞线程池中执行
+        let password = password.to_string();
+        let password_hash = tokio::task::spawn_blocking(move || hash_password(&password)).await??;
+
+        let mut transaction = begin_logged(pool.write_pool()).await?;
+        info!("开始事务注册用户 - 用户名: {}", username);
+
+        log_bind(
+            "INSERT_USER_WITH_PASSWORD_SQL",
+            &[("username", &username), ("email", &email), ("password_hash", &password_hash)],
+        );
+        match sqlx::query(&pool.schema().sql(INSERT_USER_WITH_PASSWORD_SQL))
+            .bind(username)
+            .bind(email)
+            .bind(&password_hash)
+            .execute(&mut *transaction)
+            .await
+        {
+            Ok(result) => {
+                let user_id = result.last_insert_id();
+                info!("事务中注册用户成功 - ID: {}", user_id);
+
+                transaction.commit().await?;
+                info!("事务提交成功");
+
+                Ok(user_id)
+            }
+            Err(e) => {
+                error!("注册用户失败: {}", e);
+                transaction.rollback().await?;
+                error!("事务已回滚");
+                if is_duplicate_key(&e) {
+                    Err(AppError::Duplicate(format!("用户名 {} 或邮箱 {} 已被占用", username, email)).into())
+                } else {
+                    Err(AppError::from(e).into())
+                }
+            }
+        }
+    }
+
+    // 校验用户密码，用户不存在或未设置密码时返回 false
+    pub async fn verify_password(pool: &impl WritePool, username: &str, password: &str) -> Result<bool> {
+        let password_hash: Option<Option<String>> = sqlx::query_scalar(&pool.schema().sql(SELECT_PASSWORD_HASH_BY_USERNAME_SQL))
+            .bind(username)
+            .fetch_optional(pool.write_pool())
+            .await
+            .map_err(AppError::from)?;
+
+        let Some(password_hash) = password_hash.flatten() else {
+            info!("用户不存在或未设置密码 - 用户名: {}", username);
+            return Ok(false);
+        };
+
+        let password = password.to_string();
+        let matched = tokio::task::spawn_blocking(move || verify_password_hash(&password, &password_hash)).await?;
+        info!("密码校验完成 - 用户名: {}, 结果: {}", username, matched);
+        Ok(matched)
+    }
+
+    // 按邮箱查询用户，不存在时使用给定用户名创建，返回用户以及是否为新建
+    // 两个请求并发创建同一邮箱时，后提交的一方会遇到唯一键冲突，此时重新查询并返回已存在的用户
+    pub async fn get_or_create_user_by_email(pool: &impl WritePool, email: &str, username: &str) -> Result<(User, bool)> {
+        validate_email(email)?;
+
+        let result = with_transaction(pool.write_pool(), async |transaction| {
+            let existing = sqlx::query_as::<_, User>(&pool.schema().sql(SELECT_USER_BY_EMAIL_SQL))
+                .bind(email)
+                .fetch_optional(&mut **transaction)
+                .await?;
+            if let Some(user) = existing {
+                return Ok((user, false));
+            }
+
+            log_bind("INSERT_USER_SQL", &[("username", &username), ("email", &email)]);
+            let user_id = sqlx::query(&pool.schema().sql(INSERT_USER_SQL))
+                .bind(username)
+                .bind(email)
+                .execute(&mut **transaction)
+                .await?
+                .last_insert_id();
+            let user = sqlx::query_as::<_, User>(&pool.schema().sql(SELECT_USER_BY_ID_SQL))
+                .bind(user_id)
+                .fetch_one(&mut **transaction)
+                .await?;
+            Ok((user, true))
+        })
+        .await;
+
+        match result {
+            Ok((user, created)) => {
+                info!("按邮箱获取或创建用户成功 - ID: {}, 是否新建: {}", user.id, created);
+                Ok((user, created))
+            }
+            Err(e) if is_duplicate_key_error(&e) => {
+                // 事务已回滚，在新的查询中读取并发请求刚提交的用户
+                warn!("按邮箱创建用户时发生唯一键冲突，重新查询 - 邮箱: {}", email);
+                match crate::database::select_user_by_email(&pool.read_primary(), email).await? {
+                    Some(user) => Ok((user, false)),
+                    None => Err(AppError::Duplicate(format!("用户名 {} 已被占用", username)).into()),
+                }
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    // 插入或更新用户（用户名已存在时更新邮箱，使用事务确保提交，失败时回滚）
+    //
+    // 返回 (用户ID, 是否新建)。开启 CLIENT_FOUND_ROWS 时 rows_affected() 无法区分
+    // "新插入" 和 "值未变化的更新"，因此先在事务中锁定查询用户名是否已存在。
+    // ON DUPLICATE KEY UPDATE 对 email 的唯一键同样生效，邮箱属于其他用户时会改写那个用户，
+    // 所以同时锁定查询邮箱的所有者，属于其他用户时返回 AppError::Duplicate。
+    pub async fn upsert_user(pool: &impl WritePool, username: &str, email: &str) -> Result<(u64, bool)> {
+        validate_email(email)?;
+
+        let mut transaction = begin_logged(pool.write_pool()).await?;
+        info!("开始事务插入或更新用户 - 用户名: {}", username);
+
+        let existing_id: Option<u64> = sqlx::query_scalar(&pool.schema().sql(SELECT_USER_ID_BY_USERNAME_FOR_UPDATE_SQL))
+            .bind(username)
+            .fetch_optional(&mut *transaction)
+            .await
+            .map_err(AppError::from)?;
+        let email_owner_id: Option<u64> = sqlx::query_scalar(&pool.schema().sql(SELECT_USER_ID_BY_EMAIL_FOR_UPDATE_SQL))
+            .bind(email)
+            .fetch_optional(&mut *transaction)
+            .await
+            .map_err(AppError::from)?;
+        if let Some(owner_id) = email_owner_id
+            && Some(owner_id) != existing_id
+        {
+            transaction.rollback().await?;
+            warn!("邮箱 {} 已属于其他用户 (ID: {})，事务已回滚", email, owner_id);
+            return Err(AppError::Duplicate(format!("邮箱 {} 已被占用", email)).into());
+        }
+
+        log_bind("INSERT_USER_UPSERT_SQL", &[("username", &username), ("email", &email)]);
+        match sqlx::query(&pool.schema().sql(INSERT_USER_UPSERT_SQL))
+            .bind(username)
+            .bind(email)
+            .execute(&mut *transaction)
+            .await
+        {
+            Ok(result) => {
+                let user_id = result.last_insert_id();
+                let created = existing_id.is_none();
+                if created {
+                    info!("事务中插入新用户成功 - ID: {}", user_id);
+                } else {
+                    info!("事务中更新已存在用户成功 - ID: {}", user_id);
+                }
+
+                transaction.commit().await?;
+                info!("事务提交成功");
+
+                Ok((user_id, created))
+            }
+            Err(e) => {
+                error!("插入或更新用户失败: {}", e);
+                transaction.rollback().await?;
+                error!("事务已回滚");
+                Err(AppError::from(e).into())
+            }
+        }
+    }
+
+    // 批量插入用户（单条多行 INSERT，使用事务确保提交，失败时回滚）
+    //
+    // MySQL 的多行 INSERT 中 last_insert_id() 只返回第一行的 ID，
+    // 其余行的 ID 按 "第一行 ID + 行偏移量" 计算，依赖自增 ID 在同一语句内连续分配。
+    pub async fn insert_users_batch(pool: &impl WritePool, users: &[(String, String)]) -> Result<Vec<u64>> {
+        if users.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut transaction = begin_logged(pool.write_pool()).await?;
+        info!("开始事务批量插入 {} 个用户", users.len());
+
+        match insert_users_rows(&mut transaction, pool.schema(), users).await {
+            Ok(ids) => {
+                info!("事务中批量插入用户成功 - 第一个ID: {}, 数量: {}", ids[0], ids.len());
+
+                transaction.commit().await?;
+                info!("事务提交成功");
+
+                Ok(ids)
+            }
+            Err(e) => {
+                error!("批量插入用户失败: {}", e);
+                transaction.rollback().await?;
+                error!("事务已回滚");
+                Err(AppError::from(e).into())
+            }
+        }
+    }
+
+    // 填充指定数量的随机用户（单个事务内按每批 SEED_CHUNK_SIZE 行执行多行 INSERT）
+    pub async fn seed_users(pool: &impl WritePool, count: usize) -> Result<Vec<u64>> {
+        if count == 0 {
+            return Ok(Vec::new());
+        }
+
+        let users: Vec<(String, String)> = (0..count)
+            .map(|_| (generate_default_username(), generate_random_email_default()))
+            .collect();
+
+        info!("开始事务填充 {} 个随机用户", count);
+        let ids = with_transaction(pool.write_pool(), async |transaction| {
+            let mut ids = Vec::with_capacity(count);
+            for chunk in users.chunks(SEED_CHUNK_SIZE) {
+                ids.extend(insert_users_rows(transaction, pool.schema(), chunk).await?);
+                info!("填充进度: {}/{}", ids.len(), count);
+            }
+            Ok(ids)
+        })
+        .await?;
+
+        info!("事务提交成功 - 已填充 {} 个用户", ids.len());
+        Ok(ids)
+    }
+
+    // 更新用户邮箱（使用事务确保提交，失败时回滚），返回记录是否确实被修改
+    pub async fn update_user_email(pool: &impl WritePool, user_id: u64) -> Result<bool> {
+        let user = crate::database::select_user_by_id_required(&pool.read_primary(), user_id).await?;
+        let new_email = format!("updated_{}", user.email);
+        
+        let mut transaction = begin_logged(pool.write_pool()).await?;
+        info!("开始事务更新用户邮箱");
+        
+        log_bind("UPDATE_USER_SQL", &[("email", &new_email), ("id", &user_id)]);
+        match sqlx::query(&pool.schema().sql(UPDATE_USER_SQL))
+            .bind(&new_email)
+            .bind(user_id)
+            .execute(&mut *transaction)
+            .await
+        {
+            Ok(result) if result.rows_affected() == 0 => {
+                transaction.rollback().await?;
+                warn!("更新用户邮箱未影响任何行，事务已回滚 - ID: {}", user_id);
+                Err(AppError::NotFound(format!("ID为 {} 的用户", user_id)).into())
+            }
+            Ok(_) => {
+                transaction.commit().await?;
+                info!("事务提交成功");
+                info!("更新用户邮箱成功 - ID: {}, 新邮箱: {}", user_id, new_email);
+                
+                // 验证更新，并比较 updated_at 是否随 ON UPDATE CURRENT_TIMESTAMP 前进
+                let mut was_modified = false;
+                if let Some(updated_user) = crate::database::select_user_by_id(&pool.read_primary(), user_id).await? {
+                    info!("更新后的用户 - ID: {}, 用户名: {}, 邮箱: {}",
+                        updated_user.id, updated_user.username, updated_user.email);
+                    was_modified = updated_user.email != user.email;
+                    if updated_user.updated_at > user.updated_at {
+                        debug!("updated_at 已更新: {} -> {}", user.updated_at, updated_user.updated_at);
+                    } else {
+                        // 绑定的值与原值相同时 MySQL 不会修改时间戳；TIMESTAMP 精度为秒，同一秒内的更新也看不到变化
+                        debug!("updated_at 未变化: {}, 邮箱是否变化: {}", updated_user.updated_at, was_modified);
+                    }
+                }
+                Ok(was_modified)
+            }
+            Err(e) => {
+                error!("更新用户邮箱失败: {}", e);
+                transaction.rollback().await?;
+                error!("事务已回滚");
+                Err(AppError::from(e).into())
+            }
+        }
+    }
+
+    // 乐观锁更新邮箱：只有记录的 updated_at 仍等于调用方上次读取的值时才更新
+    // 返回 false 表示记录已被并发修改（或不存在），调用方应重新读取后再决定是否重试
+    // 注意 TIMESTAMP 精度为秒，同一秒内发生的两次修改无法通过 updated_at 区分
+    pub async fn update_user_email_if_unchanged(
+        pool: &impl WritePool,
+        user_id: u64,
+        expected_updated_at: DateTime<Utc>,
+        new_email: &str,
+    ) -> Result<bool> {
+        validate_email(new_email)?;
+
+        info!("开始事务按乐观锁更新用户邮箱 - ID: {}", user_id);
+        let updated = with_transaction(pool.write_pool(), async |transaction| {
+            log_bind(
+                "UPDATE_USER_EMAIL_IF_UNCHANGED_SQL",
+                &[("email", &new_email), ("id", &user_id), ("updated_at", &expected_updated_at)],
+            );
+            let result = sqlx::query(&pool.schema().sql(UPDATE_USER_EMAIL_IF_UNCHANGED_SQL))
+                .bind(new_email)
+                .bind(user_id)
+                .bind(expected_updated_at)
+                .execute(&mut **transaction)
+                .await?;
+            Ok(result.rows_affected() > 0)
+        })
+        .await?;
+
+        if updated {
+            info!("事务提交成功 - 用户 {} 邮箱已更新为 {}", user_id, new_email);
+        } else {
+            warn!("用户 {} 已被并发修改或不存在，未更新邮箱", user_id);
+        }
+        Ok(updated)
+    }
+
+    // 批量更新用户邮箱（整批在同一事务中执行，任一更新失败则全部回滚），返回受影响的总行数
+    pub async fn update_emails_bulk(pool: &impl WritePool, updates: &[(u64, String)]) -> Result<u64> {
+        // 先校验所有邮箱，避免写入一部分后才发现格式错误
+        for (_, email) in updates {
+            validate_email(email)?;
+        }
+        if updates.is_empty() {
+            return Ok(0);
+        }
+
+        info!("开始事务批量更新 {} 个用户的邮箱", updates.len());
+        let sql = pool.schema().sql(UPDATE_USER_SQL);
+        let total = with_transaction(pool.write_pool(), async |transaction| {
+            // 预处理一次语句，循环中重复使用
+            let statement = (&mut **transaction).prepare(&sql).await?;
+            let mut total = 0;
+            for (user_id, email) in updates {
+                log_bind("UPDATE_USER_SQL", &[("email", email), ("id", user_id)]);
+                let result = statement
+                    .query()
+                    .bind(email)
+                    .bind(user_id)
+                    .execute(&mut **transaction)
+                    .await?;
+                debug!("更新用户邮箱 - ID: {}, 新邮箱: {}, 影响行数: {}", user_id, email, result.rows_affected());
+                total += result.rows_affected();
+            }
+            Ok(total)
+        })
+        .await?;
+
+        info!("事务提交成功 - 批量更新邮箱共影响 {} 行", total);
+        Ok(total)
+    }
+
+    // 将所有 @old_domain 邮箱改写为 @new_domain（在同一事务中执行），返回受影响的行数
+    //
+    // 提交前先检查改写后是否会与现有邮箱重复，存在冲突时记录每一条冲突并回滚，返回 AppError::Duplicate。
+    // 用 SUBSTRING_INDEX 重新拼接而不是 REPLACE，因为 LIKE 不区分大小写而 REPLACE 区分，
+    // 大小写不同的域名（如 @OLD.COM）会被匹配到却不会被替换。
+    pub async fn migrate_email_domain(pool: &impl WritePool, old_domain: &str, new_domain: &str) -> Result<u64> {
+        for domain in [old_domain, new_domain] {
+            if domain.is_empty() || domain.contains('@') {
+                return Err(AppError::Validation(format!("域名格式不正确: {:?}", domain)).into());
+            }
+        }
+        validate_email(&format!("user@{}", new_domain))?;
+        let old_pattern = escape_like(old_domain);
+
+        info!("开始事务迁移邮箱域名 - {} -> {}", old_domain, new_domain);
+        let migrated = with_transaction(pool.write_pool(), async |transaction| {
+            let conflicts = sqlx::query_as::<_, (u64, String, u64)>(&pool.schema().sql(SELECT_EMAIL_DOMAIN_CONFLICTS_SQL))
+                .bind(new_domain)
+                .bind(&old_pattern)
+                .fetch_all(&mut **transaction)
+                .await?;
+            if !conflicts.is_empty() {
+                for (user_id, email, conflicting_id) in &conflicts {
+                    warn!("迁移后邮箱冲突 - 用户 {} ({}) 与用户 {} 重复", user_id, email, conflicting_id);
+                }
+                return Err(AppError::Duplicate(format!(
+                    "迁移到 @{} 后有 {} 个邮箱与现有用户重复",
+                    new_domain,
+                    conflicts.len()
+                ))
+                .into());
+            }
+
+            log_bind("MIGRATE_EMAIL_DOMAIN_SQL", &[("new_domain", &new_domain), ("old_domain", &old_pattern)]);
+            match sqlx::query(&pool.schema().sql(MIGRATE_EMAIL_DOMAIN_SQL))
+                .bind(new_domain)
+                .bind(&old_pattern)
+                .execute(&mut **transaction)
                 .await
             {
-                Ok(_) => {
-                    transaction.commit().await?;
-                    info!("事务提交成功");
-                    info!("更新用户邮箱成功 - ID: {}, 新邮箱: {}", user_id, new_email);
-                    
-                    // 验证更新
-                    if let Some(updated_user) = crate::database::select_user_by_id(pool, user_id).await? {
-                        info!("更新后的用户 - ID: {}, 用户名: {}, 邮箱: {}",
-                            updated_user.id, updated_user.username, updated_user.email);
-                    }
-                    Ok(())
+                Ok(result) => Ok(result.rows_affected()),
+                // 检查之后仍可能有并发写入造成重复，由唯一约束兜底
+                Err(e) if is_duplicate_key(&e) => {
+                    Err(AppError::Duplicate(format!("迁移到 @{} 时出现重复邮箱", new_domain)).into())
                 }
-                Err(e) => {
-                    error!("更新用户邮箱失败: {}", e);
-                    transaction.rollback().await?;
-                    error!("事务已回滚");
-                    Err(e.into())
+                Err(e) => Err(AppError::from(e).into()),
+            }
+        })
+        .await?;
+
+        info!("事务提交成功 - 迁移邮箱域名共影响 {} 行", migrated);
+        Ok(migrated)
+    }
+
+    // 更新用户名（使用事务确保提交，失败时回滚，用户名重复时返回明确的错误）
+    pub async fn update_username(pool: &impl WritePool, user_id: u64, new_username: &str) -> Result<()> {
+        let mut transaction = begin_logged(pool.write_pool()).await?;
+        info!("开始事务更新用户名");
+
+        log_bind("UPDATE_USERNAME_SQL", &[("username", &new_username), ("id", &user_id)]);
+        match sqlx::query(&pool.schema().sql(UPDATE_USERNAME_SQL))
+            .bind(new_username)
+            .bind(user_id)
+            .execute(&mut *transaction)
+            .await
+        {
+            Ok(result) if result.rows_affected() == 0 => {
+                transaction.rollback().await?;
+                warn!("更新用户名未影响任何行，事务已回滚 - ID: {}", user_id);
+                Err(AppError::NotFound(format!("ID为 {} 的用户", user_id)).into())
+            }
+            Ok(_) => {
+                transaction.commit().await?;
+                info!("事务提交成功");
+                info!("更新用户名成功 - ID: {}, 新用户名: {}", user_id, new_username);
+
+                // 验证更新
+                if let Some(updated_user) = crate::database::select_user_by_id(&pool.read_primary(), user_id).await? {
+                    info!("更新后的用户 - ID: {}, 用户名: {}, 邮箱: {}",
+                        updated_user.id, updated_user.username, updated_user.email);
                 }
+                Ok(())
             }
+            Err(e) => {
+                error!("更新用户名失败: {}", e);
+                transaction.rollback().await?;
+                error!("事务已回滚");
+                if is_duplicate_key(&e) {
+                    Err(AppError::Duplicate(format!("用户名 {} 已被占用", new_username)).into())
+                } else {
+                    Err(AppError::from(e).into())
+                }
+            }
+        }
+    }
+
+    // 按ID列表批量删除用户（使用事务确保提交，失败时回滚），返回删除的行数
+    //
+    // profiles 表的外键为 ON DELETE CASCADE，被删除用户的 profile 也会一并删除。
+    pub async fn delete_users_by_ids(pool: &impl WritePool, ids: &[u64]) -> Result<u64> {
+        if ids.is_empty() {
+            return Ok(0);
+        }
+
+        let placeholders = vec!["?"; ids.len()].join(", ");
+        let sql = format!("{}({})", DELETE_USERS_BY_IDS_SQL_PREFIX, placeholders);
+        let sql = pool.schema().sql(&sql);
+
+        let mut query = sqlx::query(&sql);
+        for id in ids {
+            query = query.bind(id);
+        }
+
+        let mut transaction = begin_logged(pool.write_pool()).await?;
+        info!("开始事务批量删除 {} 个用户", ids.len());
+
+        match query.execute(&mut *transaction).await {
+            Ok(result) => {
+                let deleted = result.rows_affected();
+                transaction.commit().await?;
+                info!("事务提交成功");
+                info!("批量删除用户成功 - 删除数量: {}", deleted);
+                Ok(deleted)
+            }
+            Err(e) => {
+                error!("批量删除用户失败: {}", e);
+                transaction.rollback().await?;
+                error!("事务已回滚");
+                Err(AppError::from(e).into())
+            }
+        }
+    }
+
+    // 删除用户并报告被级联删除的 profile 数量，便于写入审计日志
+    // 在同一事务中先加锁统计 profile 数量再删除用户，用户不存在时返回 user_deleted = false 而不是错误
+    pub async fn delete_user_cascade(pool: &impl WritePool, user_id: u64) -> Result<DeleteReport> {
+        info!("开始事务级联删除用户 - ID: {}", user_id);
+        let report = with_transaction(pool.write_pool(), async |transaction| {
+            let profiles = sqlx::query_scalar::<_, i64>(&pool.schema().sql(COUNT_PROFILES_BY_USER_ID_FOR_UPDATE_SQL))
+                .bind(user_id)
+                .fetch_one(&mut **transaction)
+                .await?;
+            let result = sqlx::query(&pool.schema().sql(DELETE_USER_SQL))
+                .bind(user_id)
+                .execute(&mut **transaction)
+                .await?;
+            let user_deleted = result.rows_affected() > 0;
+            Ok(DeleteReport {
+                user_deleted,
+                profiles_deleted: if user_deleted { profiles as u64 } else { 0 },
+            })
+        })
+        .await?;
+
+        if report.user_deleted {
+            info!("事务提交成功 - 删除用户 {}，级联删除 {} 个 profile", user_id, report.profiles_deleted);
         } else {
-            Err(anyhow::anyhow!("未找到ID为 {} 的用户", user_id))
+            warn!("级联删除未找到用户 - ID: {}", user_id);
         }
+        Ok(report)
+    }
+
+    // 启用或停用用户（停用不会删除数据，可以随时重新启用），用户不存在时返回 AppError::NotFound
+    pub async fn set_user_active(pool: &impl WritePool, user_id: u64, active: bool) -> Result<()> {
+        with_transaction(pool.write_pool(), async |transaction| {
+            let result = sqlx::query(&pool.schema().sql(SET_USER_ACTIVE_SQL))
+                .bind(active)
+                .bind(user_id)
+                .execute(&mut **transaction)
+                .await?;
+            if result.rows_affected() == 0 {
+                return Err(AppError::NotFound(format!("ID为 {} 的用户", user_id)).into());
+            }
+            Ok(())
+        })
+        .await?;
+        info!("设置用户启用状态 - ID: {}, 启用: {}", user_id, active);
+        Ok(())
+    }
+
+    // 软删除用户：只记录 deleted_at，之后由 spawn_prune_task 定期物理删除
+    // 返回 false 表示用户不存在或已被软删除
+    pub async fn soft_delete_user(pool: &impl WritePool, user_id: u64) -> Result<bool> {
+        let result = sqlx::query(&pool.schema().sql(SOFT_DELETE_USER_SQL))
+            .bind(user_id)
+            .execute(pool.write_pool())
+            .await
+            .map_err(AppError::from)?;
+        let deleted = result.rows_affected() > 0;
+        info!("软删除用户 - ID: {}, 是否标记: {}", user_id, deleted);
+        Ok(deleted)
     }
 
     // 删除最早的用户（使用事务确保提交，失败时回滚）
-    pub async fn delete_oldest_user(pool: &Pool<MySql>) -> Result<()> {
-        if let Some(oldest_user) = crate::database::find_oldest_user(pool).await? {
+    pub async fn delete_oldest_user(pool: &impl WritePool) -> Result<()> {
+        if let Some(oldest_user) = crate::database::find_oldest_user(&pool.read_primary()).await? {
             info!("找到最早的用户 - ID: {}, 用户名: {}, 邮箱: {}",
                 oldest_user.id, oldest_user.username, oldest_user.email);
             
-            let mut transaction = pool.begin().await?;
+            let mut transaction = begin_logged(pool.write_pool()).await?;
             info!("开始事务删除用户");
             
-            match sqlx::query(DELETE_USER_SQL)
+            match sqlx::query(&pool.schema().sql(DELETE_USER_SQL))
                 .bind(oldest_user.id)
                 .execute(&mut *transaction)
                 .await
             {
+                Ok(result) if result.rows_affected() == 0 => {
+                    transaction.rollback().await?;
+                    warn!("删除用户未影响任何行，事务已回滚 - ID: {}", oldest_user.id);
+                    Err(AppError::NotFound(format!("ID为 {} 的用户", oldest_user.id)).into())
+                }
                 Ok(_) => {
                     transaction.commit().await?;
                     info!("事务提交成功");
@@ -107,99 +717,404 @@ impl UserService {
                     error!("删除用户失败: {}", e);
                     transaction.rollback().await?;
                     error!("事务已回滚");
-                    Err(e.into())
+                    Err(AppError::from(e).into())
                 }
             }
         } else {
-            Err(anyhow::anyhow!("未找到可删除的用户"))
+            Err(AppError::NotFound("可删除的用户".to_string()).into())
         }
     }
 }
 
+// Profile 服务
+pub struct ProfileService;
+
+impl ProfileService {
+    // 插入 profile（使用事务确保提交，失败时回滚），返回新 profile 的 ID
+    pub async fn insert_profile(
+        pool: &impl WritePool,
+        user_id: u64,
+        full_name: &str,
+        bio: Option<&str>,
+        avatar_url: Option<&str>,
+    ) -> Result<u64> {
+        if let Some(bio) = bio {
+            validate_bio(bio)?;
+        }
+        if let Some(url) = avatar_url {
+            validate_url(url)?;
+        }
+
+        let mut transaction = begin_logged(pool.write_pool()).await?;
+        info!("开始事务插入 profile - user_id: {}", user_id);
+
+        match sqlx::query(&pool.schema().sql(INSERT_PROFILE_SQL))
+            .bind(user_id)
+            .bind(full_name)
+            .bind(bio)
+            .bind(avatar_url)
+            .execute(&mut *transaction)
+            .await
+        {
+            Ok(result) => {
+                let profile_id = result.last_insert_id();
+                info!("事务中插入 profile 成功 - ID: {}", profile_id);
+
+                transaction.commit().await?;
+                info!("事务提交成功");
+
+                Ok(profile_id)
+            }
+            Err(e) => {
+                error!("插入 profile 失败: {}", e);
+                transaction.rollback().await?;
+                error!("事务已回滚");
+                Err(AppError::from(e).into())
+            }
+        }
+    }
+
+    // 插入或更新 profile（user_id 已有 profile 时更新姓名、简介和头像，使用事务确保提交，失败时回滚）
+    // 返回 profile ID，重复调用结果相同，适合 OAuth 等同步流程
+    pub async fn upsert_profile(
+        pool: &impl WritePool,
+        user_id: u64,
+        full_name: &str,
+        bio: Option<&str>,
+        avatar_url: Option<&str>,
+    ) -> Result<u64> {
+        if let Some(bio) = bio {
+            validate_bio(bio)?;
+        }
+        if let Some(url) = avatar_url {
+            validate_url(url)?;
+        }
+
+        info!("开始事务插入或更新 profile - user_id: {}", user_id);
+        let profile_id = with_transaction(pool.write_pool(), async |transaction| {
+            let result = sqlx::query(&pool.schema().sql(UPSERT_PROFILE_SQL))
+                .bind(user_id)
+                .bind(full_name)
+                .bind(bio)
+                .bind(avatar_url)
+                .execute(&mut **transaction)
+                .await?;
+            Ok(result.last_insert_id())
+        })
+        .await?;
+
+        info!("事务提交成功 - 插入或更新 profile 成功, ID: {}", profile_id);
+        Ok(profile_id)
+    }
+
+    // 更新 profile（使用事务确保提交，失败时回滚）
+    pub async fn update_profile(
+        pool: &impl WritePool,
+        user_id: u64,
+        full_name: &str,
+        bio: Option<&str>,
+        avatar_url: Option<&str>,
+    ) -> Result<()> {
+        if let Some(bio) = bio {
+            validate_bio(bio)?;
+        }
+        if let Some(url) = avatar_url {
+            validate_url(url)?;
+        }
+
+        let mut transaction = begin_logged(pool.write_pool()).await?;
+        info!("开始事务更新 profile - user_id: {}", user_id);
+
+        match sqlx::query(&pool.schema().sql(UPDATE_PROFILE_SQL))
+            .bind(full_name)
+            .bind(bio)
+            .bind(avatar_url)
+            .bind(user_id)
+            .execute(&mut *transaction)
+            .await
+        {
+            Ok(result) if result.rows_affected() == 0 => {
+                transaction.rollback().await?;
+                warn!("更新 profile 未影响任何行，事务已回滚 - user_id: {}", user_id);
+                Err(AppError::NotFound(format!("user_id 为 {} 的 profile", user_id)).into())
+            }
+            Ok(_) => {
+                transaction.commit().await?;
+                info!("事务提交成功");
+                info!("更新 profile 成功 - user_id: {}", user_id);
+                Ok(())
+            }
+            Err(e) => {
+                error!("更新 profile 失败: {}", e);
+                transaction.rollback().await?;
+                error!("事务已回滚");
+                Err(AppError::from(e).into())
+            }
+        }
+    }
+
+    // 部分更新 profile：只修改 UpdateProfile 中提供的字段（使用事务确保提交，失败时回滚）
+    // 没有提供任何字段时返回 AppError::Validation，profile 不存在时返回 AppError::NotFound
+    pub async fn update_profile_fields(pool: &impl WritePool, user_id: u64, update: &UpdateProfile) -> Result<()> {
+        if update.is_empty() {
+            return Err(AppError::Validation("没有提供需要更新的 profile 字段".to_string()).into());
+        }
+        if let Some(Some(bio)) = &update.bio {
+            validate_bio(bio)?;
+        }
+        if let Some(Some(url)) = &update.avatar_url {
+            validate_url(url)?;
+        }
+
+        let sql = format!("{}{} WHERE user_id = ?", UPDATE_PROFILE_FIELDS_SQL_PREFIX, update.set_clause());
+        let sql = pool.schema().sql(&sql);
+        let mut query = sqlx::query(&sql);
+        if let Some(full_name) = &update.full_name {
+            query = query.bind(full_name);
+        }
+        if let Some(bio) = &update.bio {
+            query = query.bind(bio);
+        }
+        if let Some(avatar_url) = &update.avatar_url {
+            query = query.bind(avatar_url);
+        }
+        query = query.bind(user_id);
+
+        info!("开始事务部分更新 profile - user_id: {}, 字段: {}", user_id, update.set_clause());
+        with_transaction(pool.write_pool(), async |transaction| {
+            let result = query.execute(&mut **transaction).await?;
+            if result.rows_affected() == 0 {
+                warn!("部分更新 profile 未影响任何行 - user_id: {}", user_id);
+                return Err(AppError::NotFound(format!("user_id 为 {} 的 profile", user_id)).into());
+            }
+            Ok(())
+        })
+        .await?;
+
+        info!("部分更新 profile 成功 - user_id: {}", user_id);
+        Ok(())
+    }
+
+    // 删除 profile（使用事务确保提交，失败时回滚），不影响用户记录
+    pub async fn delete_profile(pool: &impl WritePool, user_id: u64) -> Result<()> {
+        let mut transaction = begin_logged(pool.write_pool()).await?;
+        info!("开始事务删除 profile - user_id: {}", user_id);
+
+        match sqlx::query(&pool.schema().sql(DELETE_PROFILE_SQL))
+            .bind(user_id)
+            .execute(&mut *transaction)
+            .await
+        {
+            Ok(result) if result.rows_affected() == 0 => {
+                transaction.rollback().await?;
+                warn!("删除 profile 未影响任何行，事务已回滚 - user_id: {}", user_id);
+                Err(AppError::NotFound(format!("user_id 为 {} 的 profile", user_id)).into())
+            }
+            Ok(_) => {
+                transaction.commit().await?;
+                info!("事务提交成功");
+                info!("删除 profile 成功 - user_id: {}", user_id);
+                Ok(())
+            }
+            Err(e) => {
+                error!("删除 profile 失败: {}", e);
+                transaction.rollback().await?;
+                error!("事务已回滚");
+                Err(AppError::from(e).into())
+            }
+        }
+    }
+
+    // 将 profile 从一个用户转移给另一个用户（使用事务，任一检查失败则回滚）
+    pub async fn reassign_profile(pool: &impl WritePool, from_user_id: u64, to_user_id: u64) -> Result<()> {
+        if from_user_id == to_user_id {
+            return Err(AppError::Validation("源用户和目标用户不能相同".to_string()).into());
+        }
+
+        info!("开始事务转移 profile - 从用户 {} 到用户 {}", from_user_id, to_user_id);
+        with_transaction(pool.write_pool(), async |transaction| {
+            // 锁定两个用户，确保转移期间不会被删除
+            for user_id in [from_user_id, to_user_id] {
+                sqlx::query_scalar::<_, u64>(&pool.schema().sql(SELECT_USER_ID_FOR_UPDATE_SQL))
+                    .bind(user_id)
+                    .fetch_optional(&mut **transaction)
+                    .await?
+                    .ok_or_else(|| AppError::NotFound(format!("ID为 {} 的用户", user_id)))?;
+            }
+
+            let existing = sqlx::query_as::<_, Profile>(&pool.schema().sql(SELECT_PROFILE_BY_USER_ID_SQL))
+                .bind(to_user_id)
+                .fetch_optional(&mut **transaction)
+                .await?;
+            if existing.is_some() {
+                return Err(AppError::Duplicate(format!("目标用户 {} 已有 profile", to_user_id)).into());
+            }
+
+            let result = sqlx::query(&pool.schema().sql(REASSIGN_PROFILE_SQL))
+                .bind(to_user_id)
+                .bind(from_user_id)
+                .execute(&mut **transaction)
+                .await
+                .map_err(|e| -> anyhow::Error {
+                    // 并发情况下目标用户可能刚创建了 profile，由 user_id 的唯一约束兜底
+                    if is_duplicate_key(&e) {
+                        AppError::Duplicate(format!("目标用户 {} 已有 profile", to_user_id)).into()
+                    } else {
+                        AppError::from(e).into()
+                    }
+                })?;
+            if result.rows_affected() == 0 {
+                return Err(AppError::NotFound(format!("user_id 为 {} 的 profile", from_user_id)).into());
+            }
+            Ok(())
+        })
+        .await?;
+
+        info!("事务提交成功 - profile 已从用户 {} 转移到用户 {}", from_user_id, to_user_id);
+        Ok(())
+    }
+}
+
 // 用户和 Profile 组合服务
 pub struct UserProfileService;
 
 impl UserProfileService {
         // 同时创建用户和 profile（使用事务确保原子性）
-        pub async fn create_user_with_profile(pool: &Pool<MySql>) -> Result<(u64, u64)> {
-            let mut transaction = pool.begin().await?;
-            info!("开始事务 - 同时创建用户和 profile");
-            
-            let username = generate_random_username();
-            let email = generate_random_email();
+        pub async fn create_user_with_profile(pool: &impl WritePool) -> Result<(u64, u64)> {
+            let username = generate_default_username();
+            let email = generate_random_email_default();
             let full_name = format!("{} Smith", username);
             let bio = Some("这是一个示例个人简介".to_string());
             let avatar_url = Some("https://example.com/avatar.png".to_string());
-            
-            // 1. 插入用户
-            match sqlx::query(INSERT_USER_SQL)
-                .bind(&username)
-                .bind(&email)
-                .execute(&mut *transaction)
-                .await
-            {
-                Ok(result) => {
-                    let user_id = result.last_insert_id();
-                    info!("事务中插入用户成功 - ID: {}", user_id);
-                    
-                    // 2. 插入 profile（使用刚生成的 user_id）
-                    match sqlx::query(INSERT_PROFILE_SQL)
-                        .bind(user_id)
-                        .bind(&full_name)
-                        .bind(&bio)
-                        .bind(&avatar_url)
-                        .execute(&mut *transaction)
-                        .await
-                    {
-                        Ok(profile_result) => {
-                            let profile_id = profile_result.last_insert_id();
-                            info!("事务中插入 profile 成功 - ID: {}", profile_id);
-                            
-                            // 提交事务
-                            transaction.commit().await?;
-                            info!("事务提交成功 - 用户和 profile 创建完成");
-                            
-                            Ok((user_id, profile_id))
-                        }
-                        Err(e) => {
-                            error!("插入 profile 失败: {}", e);
-                            transaction.rollback().await?;
-                            error!("事务已回滚 - 用户和 profile 都未创建");
-                            Err(e.into())
-                        }
-                    }
-                }
-                Err(e) => {
-                    error!("插入用户失败: {}", e);
-                    transaction.rollback().await?;
-                    error!("事务已回滚");
-                    Err(e.into())
-                }
+            if let Some(bio) = &bio {
+                validate_bio(bio)?;
+            }
+            if let Some(url) = &avatar_url {
+                validate_url(url)?;
             }
+
+            info!("开始事务 - 同时创建用户和 profile");
+            // 遇到死锁时整个事务重新执行，事务内只有两条 INSERT，重复执行是安全的
+            let create = async || with_transaction(pool.write_pool(), async |transaction| {
+                // 1. 插入用户
+                log_bind("INSERT_USER_SQL", &[("username", &username), ("email", &email)]);
+                let user_id = sqlx::query(&pool.schema().sql(INSERT_USER_SQL))
+                    .bind(&username)
+                    .bind(&email)
+                    .execute(&mut **transaction)
+                    .await?
+                    .last_insert_id();
+                info!("事务中插入用户成功 - ID: {}", user_id);
+
+                // 2. 插入 profile（使用刚生成的 user_id）
+                let profile_id = sqlx::query(&pool.schema().sql(INSERT_PROFILE_SQL))
+                    .bind(user_id)
+                    .bind(&full_name)
+                    .bind(&bio)
+                    .bind(&avatar_url)
+                    .execute(&mut **transaction)
+                    .await?
+                    .last_insert_id();
+                info!("事务中插入 profile 成功 - ID: {}", profile_id);
+
+                Ok((user_id, profile_id))
+            })
+            .await;
+            let (user_id, profile_id) =
+                retry_on_deadlock(create, CREATE_USER_WITH_PROFILE_RETRIES).await?;
+
+            info!("事务提交成功 - 用户和 profile 创建完成");
+            Ok((user_id, profile_id))
         }
     
-        // 同时更新用户邮箱和 profile 信息（使用事务确保原子性）
-        pub async fn update_user_and_profile(pool: &Pool<MySql>, user_id: u64) -> Result<()> {
-            let mut transaction = pool.begin().await?;
+        // 复制用户：在同一事务中读取源用户及其 profile，用新生成的用户名和邮箱插入新用户，
+        // 并复制 profile 的姓名、简介和头像；源用户没有 profile 时只创建用户。返回新用户ID
+        pub async fn clone_user(pool: &impl WritePool, source_id: u64) -> Result<u64> {
+            let username = generate_default_username();
+            let email = generate_random_email_default();
+
+            info!("开始事务 - 复制用户 {}", source_id);
+            let (user_id, profile_id) = with_transaction(pool.write_pool(), async |transaction| {
+                let source = sqlx::query_as::<_, User>(&pool.schema().sql(SELECT_USER_BY_ID_SQL))
+                    .bind(source_id)
+                    .fetch_optional(&mut **transaction)
+                    .await?
+                    .ok_or_else(|| AppError::NotFound(format!("ID为 {} 的用户", source_id)))?;
+                let profile = sqlx::query_as::<_, Profile>(&pool.schema().sql(SELECT_PROFILE_BY_USER_ID_SQL))
+                    .bind(source.id)
+                    .fetch_optional(&mut **transaction)
+                    .await?;
+
+                log_bind("INSERT_USER_SQL", &[("username", &username), ("email", &email)]);
+                let user_id = sqlx::query(&pool.schema().sql(INSERT_USER_SQL))
+                    .bind(&username)
+                    .bind(&email)
+                    .execute(&mut **transaction)
+                    .await?
+                    .last_insert_id();
+
+                let profile_id = match profile {
+                    Some(profile) => Some(
+                        sqlx::query(&pool.schema().sql(INSERT_PROFILE_SQL))
+                            .bind(user_id)
+                            .bind(&profile.full_name)
+                            .bind(&profile.bio)
+                            .bind(&profile.avatar_url)
+                            .execute(&mut **transaction)
+                            .await?
+                            .last_insert_id(),
+                    ),
+                    None => None,
+                };
+                Ok((user_id, profile_id))
+            })
+            .await?;
+
+            match profile_id {
+                Some(profile_id) => info!(
+                    "事务提交成功 - 复制用户 {} 为 {}，profile ID: {}",
+                    source_id, user_id, profile_id
+                ),
+                None => info!("事务提交成功 - 复制用户 {} 为 {}，源用户没有 profile", source_id, user_id),
+            }
+            Ok(user_id)
+        }
+
+        // 同时创建用户和 profile，提交后查询并返回两条完整记录
+        pub async fn create_user_with_profile_returning(pool: &impl WritePool) -> Result<(User, Profile)> {
+            let (user_id, profile_id) = Self::create_user_with_profile(pool).await?;
+
+            let user = crate::database::select_user_by_id_required(&pool.read_primary(), user_id).await?;
+            let profile = crate::database::select_profile_by_id(&pool.read_primary(), profile_id)
+                .await?
+                .ok_or_else(|| AppError::NotFound(format!("ID为 {} 的 profile", profile_id)))?;
+            Ok((user, profile))
+        }
+
+        // 同时更新用户邮箱和 profile 信息（使用事务确保原子性），返回两条 UPDATE 各自影响的行数
+        // sqlx 开启了 CLIENT_FOUND_ROWS，行数为匹配到的行数，为 0 通常说明用户或其 profile 不存在
+        pub async fn update_user_and_profile(pool: &impl WritePool, user_id: u64) -> Result<UpdateReport> {
+            let mut transaction = begin_logged(pool.write_pool()).await?;
             info!("开始事务 - 同时更新用户和 profile");
             
             // 1. 更新用户邮箱
-            let new_email = format!("updated_{}@example.com", generate_random_username());
-            match sqlx::query(UPDATE_USER_SQL)
+            let new_email = format!("updated_{}@example.com", generate_default_username());
+            log_bind("UPDATE_USER_SQL", &[("email", &new_email), ("id", &user_id)]);
+            match sqlx::query(&pool.schema().sql(UPDATE_USER_SQL))
                 .bind(&new_email)
                 .bind(user_id)
                 .execute(&mut *transaction)
                 .await
             {
-                Ok(_) => {
+                Ok(user_result) => {
                     info!("事务中更新用户邮箱成功");
                     
                     // 2. 更新 profile
-                    let new_full_name = format!("Updated {}", generate_random_username());
+                    let new_full_name = format!("Updated {}", generate_default_username());
                     let new_bio = Some("更新后的个人简介".to_string());
                     let new_avatar_url = Some("https://example.com/updated-avatar.png".to_string());
                     
-                    match sqlx::query(UPDATE_PROFILE_SQL)
+                    match sqlx::query(&pool.schema().sql(UPDATE_PROFILE_SQL))
                         .bind(&new_full_name)
                         .bind(&new_bio)
                         .bind(&new_avatar_url)
@@ -207,19 +1122,29 @@ impl UserProfileService {
                         .execute(&mut *transaction)
                         .await
                     {
-                        Ok(_) => {
+                        Ok(profile_result) => {
                             info!("事务中更新 profile 成功");
                             
                             // 提交事务
                             transaction.commit().await?;
-                            info!("事务提交成功 - 用户和 profile 更新完成");
-                            Ok(())
+                            let report = UpdateReport {
+                                user_rows: user_result.rows_affected(),
+                                profile_rows: profile_result.rows_affected(),
+                            };
+                            if report.user_rows == 0 || report.profile_rows == 0 {
+                                warn!(
+                                    "用户 {} 的多表更新有未影响任何行的语句 - 用户: {} 行, profile: {} 行",
+                                    user_id, report.user_rows, report.profile_rows
+                                );
+                            }
+                            info!("事务提交成功 - 用户和 profile 更新完成: {:?}", report);
+                            Ok(report)
                         }
                         Err(e) => {
                             error!("更新 profile 失败: {}", e);
                             transaction.rollback().await?;
                             error!("事务已回滚 - 用户和 profile 都未更新");
-                            Err(e.into())
+                            Err(AppError::from(e).into())
                         }
                     }
                 }
@@ -227,18 +1152,18 @@ impl UserProfileService {
                     error!("更新用户邮箱失败: {}", e);
                     transaction.rollback().await?;
                     error!("事务已回滚");
-                    Err(e.into())
+                    Err(AppError::from(e).into())
                 }
             }
         }
     
         // 同时删除用户和 profile（使用事务确保原子性）
-        pub async fn delete_user_and_profile(pool: &Pool<MySql>, user_id: u64) -> Result<()> {
-            let mut transaction = pool.begin().await?;
+        pub async fn delete_user_and_profile(pool: &impl WritePool, user_id: u64) -> Result<()> {
+            let mut transaction = begin_logged(pool.write_pool()).await?;
             info!("开始事务 - 同时删除用户和 profile");
             
             // 1. 删除 profile
-            match sqlx::query(DELETE_PROFILE_SQL)
+            match sqlx::query(&pool.schema().sql(DELETE_PROFILE_SQL))
                 .bind(user_id)
                 .execute(&mut *transaction)
                 .await
@@ -247,7 +1172,7 @@ impl UserProfileService {
                     info!("事务中删除 profile 成功");
                     
                     // 2. 删除用户
-                    match sqlx::query(DELETE_USER_SQL)
+                    match sqlx::query(&pool.schema().sql(DELETE_USER_SQL))
                         .bind(user_id)
                         .execute(&mut *transaction)
                         .await
@@ -264,7 +1189,7 @@ impl UserProfileService {
                             error!("删除用户失败: {}", e);
                             transaction.rollback().await?;
                             error!("事务已回滚 - 用户和 profile 都未删除");
-                            Err(e.into())
+                            Err(AppError::from(e).into())
                         }
                     }
                 }
@@ -272,27 +1197,29 @@ impl UserProfileService {
                     error!("删除 profile 失败: {}", e);
                     transaction.rollback().await?;
                     error!("事务已回滚");
-                    Err(e.into())
+                    Err(AppError::from(e).into())
                 }
             }
         }
     
-        // 多表事务回滚测试 - 故意插入重复数据来演示回滚
-        pub async fn test_multi_table_transaction_rollback(pool: &Pool<MySql>) -> Result<()> {
+        // 多表事务回滚测试 - 故意插入重复数据来演示回滚，返回事务是否确实被回滚
+        pub async fn test_multi_table_transaction_rollback(pool: &impl WritePool) -> Result<bool> {
             info!("开始多表事务回滚测试...");
-            let mut transaction = pool.begin().await?;
+            // 显式使用 REPEATABLE READ，事务内多次读取看到同一快照，不会出现幻读
+            let mut transaction = begin_with_isolation(pool.write_pool(), IsolationLevel::RepeatableRead).await?;
             info!("开始事务 - 故意在多表中插入重复数据");
             
-            // 获取当前用户列表
-            let current_users = crate::database::select_all_users(pool).await?;
+            // 在事务内获取当前用户列表，与后续的插入使用同一个连接和快照
+            let current_users = crate::database::select_all_users_tx(&mut transaction, pool.schema()).await?;
             if let Some(existing_user) = current_users.first() {
                 // 故意使用重复的用户名来触发唯一约束错误
                 let duplicate_username = &existing_user.username;
-                let new_email = generate_random_email();
+                let new_email = generate_random_email_default();
                 
                 info!("尝试插入重复用户名: {}", duplicate_username);
                 
-                match sqlx::query(INSERT_USER_SQL)
+                log_bind("INSERT_USER_SQL", &[("username", duplicate_username), ("email", &new_email)]);
+                match sqlx::query(&pool.schema().sql(INSERT_USER_SQL))
                     .bind(duplicate_username)
                     .bind(&new_email)
                     .execute(&mut *transaction)
@@ -305,7 +1232,7 @@ impl UserProfileService {
                         let bio = Some("Test bio".to_string());
                         let avatar_url = Some("https://example.com/test.png".to_string());
                         
-                        match sqlx::query(INSERT_PROFILE_SQL)
+                        match sqlx::query(&pool.schema().sql(INSERT_PROFILE_SQL))
                             .bind(user_id)
                             .bind(&full_name)
                             .bind(&bio)
@@ -317,13 +1244,13 @@ impl UserProfileService {
                                 // 这不应该发生，因为用户名是唯一的
                                 transaction.commit().await?;
                                 warn!("意外成功插入重复用户名，这不应该发生");
-                                Ok(())
+                                Ok(false)
                             }
                             Err(e) => {
                                 error!("插入 profile 失败: {}", e);
                                 transaction.rollback().await?;
                                 info!("事务已成功回滚 - 数据一致性得到保证");
-                                Ok(())
+                                Ok(true)
                             }
                         }
                     }
@@ -333,11 +1260,11 @@ impl UserProfileService {
                         info!("事务已成功回滚 - 数据一致性得到保证");
                         
                         // 验证数据没有变化
-                        let users_after_rollback = crate::database::select_all_users(pool).await?;
-                        let profiles_after_rollback = crate::database::select_all_profiles(pool).await?;
-                        info!("回滚后用户数量: {} (与之前相同)", users_after_rollback.len());
-                        info!("回滚后 profile 数量: {} (与之前相同)", profiles_after_rollback.len());
-                        Ok(())
+                        let users_after_rollback = crate::database::count_users(&pool.read_primary()).await?;
+                        let profiles_after_rollback = crate::database::count_profiles(&pool.read_primary()).await?;
+                        info!("回滚后用户数量: {} (与之前相同)", users_after_rollback);
+                        info!("回滚后 profile 数量: {} (与之前相同)", profiles_after_rollback);
+                        Ok(true)
                     }
                 }
             } else {
@@ -346,22 +1273,23 @@ impl UserProfileService {
         }
     }
 
-    // 事务回滚测试 - 故意插入重复邮箱来演示回滚
-    pub async fn test_transaction_rollback(pool: &Pool<MySql>) -> Result<()> {
+    // 事务回滚测试 - 故意插入重复邮箱来演示回滚，返回事务是否确实被回滚
+    pub async fn test_transaction_rollback(pool: &impl WritePool) -> Result<bool> {
         info!("开始事务回滚测试...");
-        let mut transaction = pool.begin().await?;
+        let mut transaction = begin_logged(pool.write_pool()).await?;
         info!("开始事务 - 故意插入重复邮箱");
         
-        // 获取当前用户列表
-        let current_users = crate::database::select_all_users(pool).await?;
+        // 在事务内获取当前用户列表，与后续的插入使用同一个连接
+        let current_users = crate::database::select_all_users_tx(&mut transaction, pool.schema()).await?;
         if let Some(existing_user) = current_users.first() {
             // 故意使用重复的邮箱来触发唯一约束错误
             let duplicate_email = &existing_user.email;
-            let new_username = generate_random_username();
+            let new_username = generate_default_username();
             
             info!("尝试插入重复邮箱: {}", duplicate_email);
             
-            match sqlx::query(INSERT_USER_SQL)
+            log_bind("INSERT_USER_SQL", &[("username", &new_username), ("email", duplicate_email)]);
+            match sqlx::query(&pool.schema().sql(INSERT_USER_SQL))
                 .bind(&new_username)
                 .bind(duplicate_email)
                 .execute(&mut *transaction)
@@ -371,17 +1299,25 @@ impl UserProfileService {
                     // 这不应该发生，因为邮箱是唯一的
                     transaction.commit().await?;
                     warn!("意外成功插入重复邮箱，这不应该发生");
-                    Ok(())
+                    Ok(false)
                 }
                 Err(e) => {
                     error!("插入重复邮箱失败 (预期行为): {}", e);
                     transaction.rollback().await?;
                     info!("事务已成功回滚 - 数据一致性得到保证");
                     
-                    // 验证数据没有变化
-                    let users_after_rollback = crate::database::select_all_users(pool).await?;
-                    info!("回滚后用户数量: {} (与之前相同)", users_after_rollback.len());
-                    Ok(())
+                    // 验证数据没有变化 - 使用该邮箱的用户仍然只有原来的一个
+                    let count =
+                        crate::database::count_users_by_email(&pool.read_primary(), duplicate_email).await?;
+                    if count != 1 {
+                        return Err(anyhow::anyhow!(
+                            "回滚后邮箱 {} 的用户数量为 {}，应为 1",
+                            duplicate_email,
+                            count
+                        ));
+                    }
+                    info!("回滚后邮箱 {} 的用户数量: {} (与之前相同)", duplicate_email, count);
+                    Ok(true)
                 }
             }
         } else {
@@ -1,22 +1,354 @@
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use chrono::{DateTime, NaiveDateTime, Utc};
 use rand::{Rng, distributions::Alphanumeric, thread_rng};
 use rand::seq::SliceRandom;
+use sqlx::{MySql, Pool};
+use std::fmt::Display;
+use std::ops::Range;
+use tracing::debug;
 
-pub fn generate_random_username() -> String {
+use crate::database::email_exists;
+use crate::error::AppError;
+
+// 默认生成的用户名长度
+const DEFAULT_USERNAME_LEN: usize = 10;
+
+// 生成指定长度的随机用户名（先过滤掉数字再截取，保证长度准确）
+pub fn generate_random_username(len: usize) -> String {
     let mut rng = thread_rng();
     let username: String = (&mut rng)
         .sample_iter(Alphanumeric)
         .filter(|c| c.is_ascii_alphabetic())
         .map(char::from)
-        .take(10)
+        .take(len)
         .collect();
     username
 }
 
-pub fn generate_random_email() -> String {
-    let username = generate_random_username().to_lowercase();
-    let domains = ["example.com", "test.com", "mail.com", "demo.org"];
+// 生成默认长度的随机用户名
+pub fn generate_default_username() -> String {
+    generate_random_username(DEFAULT_USERNAME_LEN)
+}
+
+// 生成随机邮箱时默认使用的域名
+pub const DEFAULT_EMAIL_DOMAINS: &[&str] = &["example.com", "test.com", "mail.com", "demo.org"];
+
+// 使用指定域名列表中的随机域名生成随机邮箱，列表为空时使用默认域名
+pub fn generate_random_email(domains: &[&str]) -> String {
+    let username = generate_default_username().to_lowercase();
+    let domains = if domains.is_empty() { DEFAULT_EMAIL_DOMAINS } else { domains };
 
     let mut rng = thread_rng();
     let domain = domains.choose(&mut rng).unwrap_or(&"example.com");
     format!("{}@{}", username, domain)
-}
\ No newline at end of file
+}
+
+// 使用默认域名生成随机邮箱
+pub fn generate_random_email_default() -> String {
+    generate_random_email(DEFAULT_EMAIL_DOMAINS)
+}
+
+// 生成数据库中尚未使用的随机邮箱的最大尝试次数
+const UNIQUE_EMAIL_MAX_ATTEMPTS: usize = 10;
+
+// 生成数据库中尚未使用的随机邮箱，超过最大尝试次数仍冲突时返回错误
+pub async fn generate_unique_email(pool: &Pool<MySql>) -> anyhow::Result<String> {
+    for attempt in 1..=UNIQUE_EMAIL_MAX_ATTEMPTS {
+        let email = generate_random_email_default();
+        if !email_exists(pool, &email).await? {
+            return Ok(email);
+        }
+        debug!("随机邮箱已存在 (第 {} 次尝试): {}", attempt, email);
+    }
+    Err(anyhow::anyhow!("尝试 {} 次后仍未生成未被占用的邮箱", UNIQUE_EMAIL_MAX_ATTEMPTS))
+}
+// 校验邮箱格式：恰好一个 @，本地部分非空，域名至少包含一个点，且首尾不含空白
+pub fn validate_email(email: &str) -> Result<(), AppError> {
+    if email.trim() != email {
+        return Err(AppError::Validation(format!("邮箱首尾不能包含空白: {:?}", email)));
+    }
+
+    let mut parts = email.split('@');
+    let (local, domain) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(local), Some(domain), None) => (local, domain),
+        _ => return Err(AppError::Validation(format!("邮箱必须包含且只包含一个 @: {:?}", email))),
+    };
+
+    if local.is_empty() {
+        return Err(AppError::Validation(format!("邮箱用户名部分不能为空: {:?}", email)));
+    }
+    if !domain.contains('.') || domain.split('.').any(str::is_empty) {
+        return Err(AppError::Validation(format!("邮箱域名格式无效: {:?}", email)));
+    }
+    Ok(())
+}
+
+// profile 简介允许的最大字符数
+pub const MAX_BIO_CHARS: usize = 2000;
+
+// 校验 profile 简介长度（按字符数计算，中文等多字节字符也算一个字符）
+pub fn validate_bio(bio: &str) -> Result<(), AppError> {
+    let len = bio.chars().count();
+    if len > MAX_BIO_CHARS {
+        return Err(AppError::Validation(format!(
+            "简介长度为 {} 个字符，超过上限 {}",
+            len, MAX_BIO_CHARS
+        )));
+    }
+    Ok(())
+}
+
+// 将简介截断到最多 max 个字节，只在 UTF-8 字符边界处截断，不会切开多字节字符
+pub fn truncate_bio(bio: &str, max: usize) -> String {
+    if bio.len() <= max {
+        return bio.to_string();
+    }
+    let mut end = max;
+    while !bio.is_char_boundary(end) {
+        end -= 1;
+    }
+    bio[..end].to_string()
+}
+
+// 校验 URL：协议必须是 http 或 https，主机名非空，且不含空白字符
+// 用于头像地址等会被页面直接引用的字段，拒绝 javascript: 之类的值
+pub fn validate_url(url: &str) -> Result<(), AppError> {
+    if url.chars().any(char::is_whitespace) {
+        return Err(AppError::Validation(format!("URL 不能包含空白字符: {:?}", url)));
+    }
+
+    let rest = match url.split_once("://") {
+        Some((scheme, rest))
+            if scheme.eq_ignore_ascii_case("http") || scheme.eq_ignore_ascii_case("https") =>
+        {
+            rest
+        }
+        _ => return Err(AppError::Validation(format!("URL 协议必须是 http 或 https: {:?}", url))),
+    };
+
+    // 主机部分截止到第一个 / ? #，并去掉可能存在的用户信息和端口
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let host = authority.rsplit('@').next().unwrap_or_default();
+    let host = host.split(':').next().unwrap_or_default();
+    if host.is_empty() {
+        return Err(AppError::Validation(format!("URL 主机名不能为空: {:?}", url)));
+    }
+    Ok(())
+}
+
+// 根据多行 INSERT 返回的第一个自增ID计算整批记录的ID范围（不含 end）
+// last_insert_id() 是连接级别的值，只反映本连接上一条 INSERT 的第一行ID，
+// 不会被其他连接的并发插入影响；但由此推算后续行的ID依赖以下前提：
+// - auto_increment_increment 为 1（可用 database::check_autoinc_settings 检查）
+// - 语句是行数已知的 "simple insert"（VALUES 多行），InnoDB 会一次分配连续的ID；
+//   INSERT ... SELECT 等 "bulk insert" 在 innodb_autoinc_lock_mode = 2 时可能与并发语句交错
+pub fn last_id_for_batch(first_id: u64, count: usize) -> Range<u64> {
+    first_id..first_id + count as u64
+}
+
+// 格式化要记录到日志的绑定参数，名称包含 password 或值形如密码哈希的参数会被隐藏
+pub fn format_bind_params(params: &[(&str, &dyn Display)]) -> String {
+    params
+        .iter()
+        .map(|(name, value)| {
+            let value = value.to_string();
+            if name.contains("password") || value.starts_with("$argon2") {
+                format!("{}=***", name)
+            } else {
+                format!("{}={:?}", name, value)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// 转义 LIKE 模式中的通配符，使 % 和 _ 按字面匹配
+pub fn escape_like(input: &str) -> String {
+    let mut escaped = String::with_capacity(input.len());
+    for c in input.chars() {
+        if matches!(c, '\\' | '%' | '_') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+// 使用 Argon2 和随机盐对密码进行哈希，返回 PHC 格式字符串
+pub fn hash_password(password: &str) -> anyhow::Result<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| anyhow::anyhow!("密码哈希失败: {}", e))
+}
+
+// 校验密码与 PHC 格式的哈希是否匹配，哈希格式无效时返回 false
+pub fn verify_password_hash(password: &str, password_hash: &str) -> bool {
+    match PasswordHash::new(password_hash) {
+        Ok(parsed) => Argon2::default()
+            .verify_password(password.as_bytes(), &parsed)
+            .is_ok(),
+        Err(e) => {
+            debug!("密码哈希格式无效: {}", e);
+            false
+        }
+    }
+}
+
+// 将 MySQL 返回的不带时区的时间按 UTC 解释，转换为 DateTime<Utc>
+// sqlx 建立连接时把会话时区设为 +00:00，TIMESTAMP 列由服务器按 UTC 返回，DATETIME 列按字面值返回，
+// 本库写入 DATETIME 时也统一使用 UTC，因此这里不做任何偏移，也不受本地夏令时影响。
+// 如果通过 MySqlConnectOptions::timezone 改用了其他会话时区，需要先按该时区换算再调用
+pub fn to_utc(naive: NaiveDateTime) -> DateTime<Utc> {
+    naive.and_utc()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_to_utc_does_not_shift() {
+        // 2024-03-10 02:30 在美国东部时间处于夏令时切换的空档，按 UTC 解释时不受影响
+        let naive = NaiveDate::from_ymd_opt(2024, 3, 10).unwrap().and_hms_opt(2, 30, 0).unwrap();
+        let utc = to_utc(naive);
+        assert_eq!(utc.to_rfc3339(), "2024-03-10T02:30:00+00:00");
+        assert_eq!(utc.naive_utc(), naive);
+    }
+
+    #[test]
+    fn test_generate_random_email_uses_given_domain() {
+        let email = generate_random_email(&["corp.example"]);
+        assert!(email.ends_with("@corp.example"));
+        assert!(validate_email(&email).is_ok());
+
+        // 域名列表为空时回退到默认域名
+        let email = generate_random_email(&[]);
+        let domain = email.split('@').nth(1).unwrap();
+        assert!(DEFAULT_EMAIL_DOMAINS.contains(&domain));
+    }
+
+    #[test]
+    fn test_truncate_bio_respects_char_boundary() {
+        assert_eq!(truncate_bio("hello", 10), "hello");
+        assert_eq!(truncate_bio("hello", 3), "hel");
+        // "你" 和 "好" 各占 3 个字节，截断位置落在 "好" 中间时退回到它之前
+        assert_eq!(truncate_bio("a你好", 4), "a你");
+        assert_eq!(truncate_bio("a你好", 5), "a你");
+        assert_eq!(truncate_bio("a你好", 6), "a你");
+        assert_eq!(truncate_bio("a你好", 7), "a你好");
+        assert_eq!(truncate_bio("😀", 2), "");
+    }
+
+    #[test]
+    fn test_validate_bio_counts_chars() {
+        assert!(validate_bio(&"好".repeat(MAX_BIO_CHARS)).is_ok());
+        assert!(matches!(
+            validate_bio(&"好".repeat(MAX_BIO_CHARS + 1)),
+            Err(AppError::Validation(_))
+        ));
+    }
+
+    #[test]
+    fn test_format_bind_params_redacts_password_hash() {
+        let params: [(&str, &dyn Display); 4] = [
+            ("username", &"alice"),
+            ("id", &7u64),
+            ("password_hash", &"secret"),
+            ("note", &"$argon2id$v=19$m=19456,t=2,p=1$abc$def"),
+        ];
+        assert_eq!(
+            format_bind_params(&params),
+            r#"username="alice", id="7", password_hash=***, note=***"#
+        );
+    }
+
+    #[test]
+    fn test_validate_url_accepts_http_and_https() {
+        for url in [
+            "https://example.com/avatar.png",
+            "http://example.com",
+            "HTTPS://cdn.example.com:8443/a.png?size=64#x",
+            "https://user@example.com/a.png",
+        ] {
+            assert!(validate_url(url).is_ok(), "{}", url);
+        }
+    }
+
+    #[test]
+    fn test_validate_url_rejects_invalid() {
+        for url in [
+            "",
+            "javascript:alert(1)",
+            "data:image/png;base64,AAAA",
+            "ftp://example.com/a.png",
+            "https://",
+            "https:///a.png",
+            "https://:8080/a.png",
+            "example.com/a.png",
+            "https://example.com/a b.png",
+        ] {
+            assert!(
+                matches!(validate_url(url), Err(AppError::Validation(_))),
+                "{}",
+                url
+            );
+        }
+    }
+
+    #[test]
+    fn test_last_id_for_batch() {
+        assert_eq!(last_id_for_batch(10, 3), 10..13);
+        assert_eq!(last_id_for_batch(10, 3).collect::<Vec<_>>(), vec![10, 11, 12]);
+        assert!(last_id_for_batch(10, 0).is_empty());
+    }
+
+    #[test]
+    fn test_escape_like() {
+        assert_eq!(escape_like("alice"), "alice");
+        assert_eq!(escape_like("50%_off"), "50\\%\\_off");
+        assert_eq!(escape_like("a\\b"), "a\\\\b");
+    }
+
+    #[test]
+    fn test_hash_and_verify_password() {
+        let hash = hash_password("s3cret!").unwrap();
+        assert_ne!(hash, "s3cret!");
+        assert!(verify_password_hash("s3cret!", &hash));
+        assert!(!verify_password_hash("wrong", &hash));
+        assert!(!verify_password_hash("s3cret!", "not-a-hash"));
+    }
+
+    #[test]
+    fn test_validate_email_accepts_valid() {
+        assert!(validate_email("alice@example.com").is_ok());
+        assert!(validate_email("a.b@mail.example.org").is_ok());
+        assert!(validate_email(&generate_random_email_default()).is_ok());
+    }
+
+    #[test]
+    fn test_validate_email_rejects_invalid() {
+        let invalid = [
+            "",
+            "a@b",
+            "foo@@bar.com",
+            "@example.com",
+            "alice",
+            "alice@.com",
+            "alice@example.",
+            " alice@example.com",
+            "alice@example.com\n",
+        ];
+        for email in invalid {
+            assert!(
+                matches!(validate_email(email), Err(AppError::Validation(_))),
+                "应当拒绝: {:?}",
+                email
+            );
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use sqlx::mysql::MySqlPoolOptions;
+
+use sqlx_example::database::AppPools;
+use sqlx_example::migrations::run_migrations;
+use sqlx_example::schema::Schema;
+use sqlx_example::utils::generate_random_username;
+
+// 每次测试独立的一组测试表
+//
+// 所有测试共用 TEST_DATABASE_URL 指向的数据库，为了让测试可以并行执行，每个测试使用一个随机的表名前缀
+// （如 t_ab12cd34ef56_users），通过 AppPools::schema 传给建表、迁移、查询和服务层，结束时删除这些表。
+// 直接执行SQL的测试需要用 pool.schema.sql(...) 改写表名，需要 Pool<MySql> 的函数传入 &pool.primary。
+pub struct TestDb {
+    pub pool: AppPools,
+}
+
+impl TestDb {
+    // 读取 TEST_DATABASE_URL，以随机前缀执行迁移建表，未设置时返回 None 以跳过测试
+    pub async fn setup() -> Option<Self> {
+        let Ok(database_url) = std::env::var("TEST_DATABASE_URL") else {
+            eprintln!("未设置 TEST_DATABASE_URL，跳过数据库集成测试");
+            return None;
+        };
+
+        let primary = MySqlPoolOptions::new()
+            .max_connections(2)
+            .connect(&database_url)
+            .await
+            .expect("连接测试数据库失败");
+        let prefix = format!("t_{}_", generate_random_username(12).to_lowercase());
+        let pool = AppPools {
+            schema: Schema::new(prefix).expect("测试表名前缀无效"),
+            ..AppPools::single(primary)
+        };
+        run_migrations(&pool).await.expect("执行迁移失败");
+
+        Some(Self { pool })
+    }
+
+    // 删除本次测试的所有表
+    pub async fn teardown(self) {
+        let sql = self.pool.schema.sql("DROP TABLE IF EXISTS profiles, users, _migrations");
+        sqlx::query(&sql)
+            .execute(&self.pool.primary)
+            .await
+            .expect("删除测试表失败");
+        self.pool.primary.close().await;
+    }
+}
@@ -0,0 +1,141 @@
+use anyhow::Result;
+
+use sqlx_example::database::{
+    WritePool, count_profiles, count_users, select_all_users, select_profile_by_user_id, select_user_by_id,
+};
+use sqlx_example::models::{NewProfile, NewUser};
+use sqlx_example::{Profile, ProfileService, Repository, User, UserService};
+
+mod common;
+
+#[tokio::test]
+async fn test_user_crud_lifecycle() -> Result<()> {
+    let Some(db) = common::TestDb::setup().await else {
+        return Ok(());
+    };
+    let pool = &db.pool;
+
+    // 插入
+    let user_id = UserService::insert_user(pool).await?;
+    assert_eq!(count_users(pool).await?, 1);
+
+    // 查询
+    let user = select_user_by_id(pool, user_id).await?.expect("插入的用户应当存在");
+    assert_eq!(select_all_users(pool).await?.len(), 1);
+
+    // 更新
+    UserService::update_user_email(pool, user_id).await?;
+    let updated = select_user_by_id(pool, user_id).await?.unwrap();
+    assert_eq!(updated.email, format!("updated_{}", user.email));
+
+    UserService::update_username(pool, user_id, "renamed_user").await?;
+    let renamed = select_user_by_id(pool, user_id).await?.unwrap();
+    assert_eq!(renamed.username, "renamed_user");
+
+    // 删除
+    assert_eq!(UserService::delete_users_by_ids(pool, &[user_id]).await?, 1);
+    assert!(select_user_by_id(pool, user_id).await?.is_none());
+
+    db.teardown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_profile_crud_lifecycle() -> Result<()> {
+    let Some(db) = common::TestDb::setup().await else {
+        return Ok(());
+    };
+    let pool = &db.pool;
+
+    let user_id = UserService::insert_user(pool).await?;
+
+    let profile_id =
+        ProfileService::insert_profile(pool, user_id, "Alice", Some("bio"), None).await?;
+    let profile = select_profile_by_user_id(pool, user_id).await?.expect("插入的 profile 应当存在");
+    assert_eq!(profile.id, profile_id);
+    assert_eq!(profile.full_name, "Alice");
+
+    let avatar_url = Some("https://example.com/a.png");
+    ProfileService::update_profile(pool, user_id, "Alice Smith", None, avatar_url).await?;
+    let profile = select_profile_by_user_id(pool, user_id).await?.unwrap();
+    assert_eq!(profile.full_name, "Alice Smith");
+    assert_eq!(profile.bio, None);
+    assert_eq!(profile.avatar_url.as_deref(), avatar_url);
+
+    ProfileService::delete_profile(pool, user_id).await?;
+    assert_eq!(count_profiles(pool).await?, 0);
+    assert_eq!(count_users(pool).await?, 1);
+
+    db.teardown().await;
+    Ok(())
+}
+
+// 通过 Repository 泛型接口统计记录数
+async fn count_all<R: Repository>(pool: &impl WritePool) -> Result<usize> {
+    Ok(R::all(pool).await?.len())
+}
+
+#[tokio::test]
+async fn test_repository_lifecycle() -> Result<()> {
+    let Some(db) = common::TestDb::setup().await else {
+        return Ok(());
+    };
+    let pool = &db.pool;
+
+    let new_user = NewUser {
+        username: "repo_user".to_string(),
+        email: "repo_user@example.com".to_string(),
+    };
+    let user = User::insert(pool, &new_user).await?;
+    assert_eq!(User::find_by_id(pool, user.id).await?.unwrap().username, "repo_user");
+
+    let new_profile = NewProfile {
+        user_id: user.id,
+        full_name: "Repo User".to_string(),
+        bio: None,
+        avatar_url: None,
+    };
+    let profile = Profile::insert(pool, &new_profile).await?;
+    assert_eq!(profile.user_id, user.id);
+    assert_eq!(count_all::<User>(pool).await?, 1);
+    assert_eq!(count_all::<Profile>(pool).await?, 1);
+
+    Profile::delete(pool, profile.id).await?;
+    assert!(Profile::find_by_id(pool, profile.id).await?.is_none());
+    User::delete(pool, user.id).await?;
+    assert!(User::find_by_id(pool, user.id).await?.is_none());
+    assert!(User::delete(pool, user.id).await.is_err());
+
+    db.teardown().await;
+    Ok(())
+}
+
+#[cfg(feature = "testing")]
+#[tokio::test]
+async fn test_truncate_all_resets_tables() -> Result<()> {
+    let Some(db) = common::TestDb::setup().await else {
+        return Ok(());
+    };
+    let pool = &db.pool;
+
+    sqlx_example::UserProfileService::create_user_with_profile(pool).await?;
+    sqlx_example::database::truncate_all(pool).await?;
+    assert_eq!(count_users(pool).await?, 0);
+    assert_eq!(count_profiles(pool).await?, 0);
+
+    // 自增ID已重置
+    let user_id = UserService::insert_user(pool).await?;
+    assert_eq!(user_id, 1);
+
+    // 归还连接池的连接都已恢复外键检查（测试连接池最多 2 个连接，同时取出即覆盖全部连接）
+    let mut first = pool.primary.acquire().await?;
+    let mut second = pool.primary.acquire().await?;
+    for conn in [&mut first, &mut second] {
+        let checks: i64 = sqlx::query_scalar("SELECT @@SESSION.foreign_key_checks").fetch_one(&mut **conn).await?;
+        assert_eq!(checks, 1);
+    }
+    drop((first, second));
+
+    db.teardown().await;
+    Ok(())
+}
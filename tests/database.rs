@@ -0,0 +1,552 @@
+use anyhow::Result;
+use chrono::{Duration, NaiveDateTime, TimeZone, Utc};
+
+use sqlx_example::database::{
+    MAX_PAGE_LIMIT, can_insert_user, count_users, count_users_by_domain, create_all_tables,
+    create_all_tables_with_collation, distinct_email_domains, fetch_scalar, fetch_scalar_with,
+    fetch_stats, find_duplicate_profiles, find_orphaned_profiles, search_profiles_by_name,
+    select_all_profiles, select_recent_users, select_recently_updated_users, select_user_by_id,
+    select_user_by_username, select_user_by_username_exact, select_users_by_ids,
+    select_users_created_between, select_users_filtered, select_users_paginated,
+    select_users_without_profile, warmup_pool,
+};
+use sqlx_example::export::users_page_json;
+use sqlx_example::migrations::{MIGRATIONS, run_migrations};
+use sqlx_example::models::{Collation, UserFilter};
+use sqlx_example::utils::to_utc;
+use sqlx_example::{AppError, ProfileService, UserProfileService, UserService};
+
+mod common;
+
+#[tokio::test]
+async fn test_select_users_created_between_filters_subrange() -> Result<()> {
+    let Some(db) = common::TestDb::setup().await else {
+        return Ok(());
+    };
+    let pool = &db.pool;
+
+    // 插入四个创建时间间隔一小时的用户
+    let base = Utc.with_ymd_and_hms(2001, 1, 1, 0, 0, 0).unwrap();
+    let mut ids = Vec::new();
+    let insert = pool.schema.sql("INSERT INTO users (username, email, created_at) VALUES (?, ?, ?)");
+    for i in 0..4 {
+        let result = sqlx::query(&insert)
+            .bind(format!("user_{}", i))
+            .bind(format!("user_{}@example.com", i))
+            .bind(base + Duration::hours(i))
+            .execute(&pool.primary)
+            .await?;
+        ids.push(result.last_insert_id());
+    }
+
+    let users =
+        select_users_created_between(pool, base + Duration::hours(1), base + Duration::hours(2))
+            .await?;
+    let found: Vec<u64> = users.iter().map(|u| u.id).collect();
+    assert_eq!(found, vec![ids[1], ids[2]]);
+
+    db.teardown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_select_users_filtered_combines_conditions() -> Result<()> {
+    let Some(db) = common::TestDb::setup().await else {
+        return Ok(());
+    };
+    let pool = &db.pool;
+
+    let old = Utc.with_ymd_and_hms(2001, 1, 1, 0, 0, 0).unwrap();
+    let mut ids = Vec::new();
+    let insert = pool.schema.sql("INSERT INTO users (username, email, created_at) VALUES (?, ?, ?)");
+    for (username, email, created_at) in [
+        ("alice", "alice@a.example.com", old),
+        ("alan", "alan@b.example.com", Utc::now()),
+        ("al_x", "al_x@a.example.com", Utc::now()),
+        ("bob", "bob@a.example.com", Utc::now()),
+    ] {
+        let result = sqlx::query(&insert)
+            .bind(username)
+            .bind(email)
+            .bind(created_at)
+            .execute(&pool.primary)
+            .await?;
+        ids.push(result.last_insert_id());
+    }
+
+    let found = async |filter: UserFilter| -> Result<Vec<u64>> {
+        Ok(select_users_filtered(pool, &filter).await?.iter().map(|u| u.id).collect())
+    };
+    assert_eq!(found(UserFilter::default()).await?, ids);
+    let filter = UserFilter {
+        username_contains: Some("al".to_string()),
+        email_domain: Some("a.example.com".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(found(filter.clone()).await?, vec![ids[0], ids[2]]);
+    let filter = UserFilter { created_after: Some(old), ..filter };
+    assert_eq!(found(filter).await?, vec![ids[2]]);
+    // _ 按字面匹配
+    let filter = UserFilter { username_contains: Some("l_".to_string()), ..Default::default() };
+    assert_eq!(found(filter).await?, vec![ids[2]]);
+
+    db.teardown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_select_users_without_profile_includes_user_missing_profile() -> Result<()> {
+    let Some(db) = common::TestDb::setup().await else {
+        return Ok(());
+    };
+    let pool = &db.pool;
+
+    let lonely_id = UserService::insert_user(pool).await?;
+    let (with_profile_id, _) = UserProfileService::create_user_with_profile(pool).await?;
+
+    let ids: Vec<u64> = select_users_without_profile(pool).await?.iter().map(|u| u.id).collect();
+    assert_eq!(ids, vec![lonely_id]);
+    assert!(!ids.contains(&with_profile_id));
+
+    db.teardown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_fetch_stats_counts_users_and_profiles() -> Result<()> {
+    let Some(db) = common::TestDb::setup().await else {
+        return Ok(());
+    };
+    let pool = &db.pool;
+
+    // 一个很早以前创建的用户，不计入今天的新用户
+    sqlx::query(&pool.schema.sql("INSERT INTO users (username, email, created_at) VALUES (?, ?, ?)"))
+        .bind("old_user")
+        .bind("old_user@example.com")
+        .bind(Utc.with_ymd_and_hms(2001, 1, 1, 0, 0, 0).unwrap())
+        .execute(&pool.primary)
+        .await?;
+    UserService::insert_user(pool).await?;
+    UserProfileService::create_user_with_profile(pool).await?;
+
+    let stats = fetch_stats(pool).await?;
+    assert_eq!(stats.total_users, 3);
+    assert_eq!(stats.total_profiles, 1);
+    assert_eq!(stats.users_created_today, 2);
+
+    db.teardown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_count_users_by_domain_orders_by_count() -> Result<()> {
+    let Some(db) = common::TestDb::setup().await else {
+        return Ok(());
+    };
+    let pool = &db.pool;
+
+    for (username, email) in [
+        ("a1", "a1@a.example.com"),
+        ("b1", "b1@b.example.com"),
+        ("b2", "b2@b.example.com"),
+    ] {
+        UserService::insert_user_returning(pool, username, email).await?;
+    }
+
+    let counts: Vec<(String, i64)> = count_users_by_domain(pool)
+        .await?
+        .into_iter()
+        .map(|c| (c.domain, c.count))
+        .collect();
+    assert_eq!(
+        counts,
+        vec![("b.example.com".to_string(), 2), ("a.example.com".to_string(), 1)]
+    );
+
+    db.teardown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_distinct_email_domains_are_sorted() -> Result<()> {
+    let Some(db) = common::TestDb::setup().await else {
+        return Ok(());
+    };
+    let pool = &db.pool;
+
+    assert!(distinct_email_domains(pool).await?.is_empty());
+    for (username, email) in [
+        ("c1", "c1@c.example.com"),
+        ("a1", "a1@a.example.com"),
+        ("c2", "c2@c.example.com"),
+        ("b1", "b1@b.example.com"),
+    ] {
+        UserService::insert_user_returning(pool, username, email).await?;
+    }
+
+    assert_eq!(
+        distinct_email_domains(pool).await?,
+        ["a.example.com", "b.example.com", "c.example.com"]
+    );
+
+    db.teardown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_on_dropped_table_returns_schema_missing() -> Result<()> {
+    let Some(db) = common::TestDb::setup().await else {
+        return Ok(());
+    };
+    let pool = &db.pool;
+
+    sqlx::query(&pool.schema.sql("DROP TABLE profiles, users")).execute(&pool.primary).await?;
+
+    let err = select_all_profiles(pool).await.unwrap_err();
+    assert!(matches!(err.downcast_ref::<AppError>(), Some(AppError::SchemaMissing(_))));
+
+    // 基于 fetch_scalar 的统计和按ID查询同样会映射错误
+    let err = count_users(pool).await.unwrap_err();
+    assert!(matches!(err.downcast_ref::<AppError>(), Some(AppError::SchemaMissing(_))));
+    let err = select_user_by_id(pool, 1).await.unwrap_err();
+    assert!(matches!(err.downcast_ref::<AppError>(), Some(AppError::SchemaMissing(_))));
+
+    db.teardown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_create_all_tables_is_idempotent() -> Result<()> {
+    let Some(db) = common::TestDb::setup().await else {
+        return Ok(());
+    };
+    let pool = &db.pool;
+
+    sqlx::query(&pool.schema.sql("DROP TABLE profiles, users")).execute(&pool.primary).await?;
+    create_all_tables(pool).await?;
+    create_all_tables(pool).await?;
+    assert!(select_all_profiles(pool).await?.is_empty());
+
+    db.teardown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_binary_collation_makes_usernames_case_sensitive() -> Result<()> {
+    let Some(db) = common::TestDb::setup().await else {
+        return Ok(());
+    };
+    let pool = &db.pool;
+
+    sqlx::query(&pool.schema.sql("DROP TABLE profiles, users")).execute(&pool.primary).await?;
+    create_all_tables_with_collation(pool, Collation::Utf8mb4Bin).await?;
+    UserService::insert_user_returning(pool, "Alice", "alice1@example.com").await?;
+    UserService::insert_user_returning(pool, "alice", "alice2@example.com").await?;
+
+    let collation: String = sqlx::query_scalar(
+        "SELECT TABLE_COLLATION FROM information_schema.TABLES \
+         WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = ?",
+    )
+    .bind(pool.schema.users_table())
+    .fetch_one(&pool.primary)
+    .await?;
+    assert_eq!(collation, "utf8mb4_bin");
+
+    db.teardown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_select_user_by_username_case_sensitivity() -> Result<()> {
+    let Some(db) = common::TestDb::setup().await else {
+        return Ok(());
+    };
+    let pool = &db.pool;
+
+    let user = UserService::insert_user_returning(pool, "Alice", "alice@example.com").await?;
+
+    // 默认排序规则不区分大小写
+    assert_eq!(select_user_by_username(pool, "alice").await?.map(|u| u.id), Some(user.id));
+    assert_eq!(select_user_by_username(pool, "ALICE").await?.map(|u| u.id), Some(user.id));
+
+    assert_eq!(select_user_by_username_exact(pool, "Alice").await?.map(|u| u.id), Some(user.id));
+    assert!(select_user_by_username_exact(pool, "alice").await?.is_none());
+    assert!(select_user_by_username_exact(pool, "ALICE").await?.is_none());
+
+    db.teardown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_run_migrations_after_create_all_tables() -> Result<()> {
+    let Some(db) = common::TestDb::setup().await else {
+        return Ok(());
+    };
+    let pool = &db.pool;
+
+    // 用最新的建表语句建表后再执行迁移，已存在的列和索引应被跳过
+    sqlx::query(&pool.schema.sql("DROP TABLE profiles, users, _migrations")).execute(&pool.primary).await?;
+    create_all_tables(pool).await?;
+    run_migrations(pool).await?;
+    run_migrations(pool).await?;
+
+    let applied: i64 = sqlx::query_scalar(&pool.schema.sql("SELECT COUNT(*) FROM _migrations"))
+        .fetch_one(&pool.primary)
+        .await?;
+    assert_eq!(applied as usize, MIGRATIONS.len());
+
+    db.teardown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_select_recent_users_returns_newest_first() -> Result<()> {
+    let Some(db) = common::TestDb::setup().await else {
+        return Ok(());
+    };
+    let pool = &db.pool;
+
+    assert!(select_recent_users(pool, 5).await?.is_empty());
+
+    let base = Utc.with_ymd_and_hms(2001, 1, 1, 0, 0, 0).unwrap();
+    let mut ids = Vec::new();
+    let insert = pool.schema.sql("INSERT INTO users (username, email, created_at) VALUES (?, ?, ?)");
+    for i in 0..3 {
+        let result = sqlx::query(&insert)
+            .bind(format!("recent_{}", i))
+            .bind(format!("recent_{}@example.com", i))
+            .bind(base + Duration::hours(i))
+            .execute(&pool.primary)
+            .await?;
+        ids.push(result.last_insert_id());
+    }
+
+    let found: Vec<u64> = select_recent_users(pool, 2).await?.iter().map(|u| u.id).collect();
+    assert_eq!(found, vec![ids[2], ids[1]]);
+
+    db.teardown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_can_insert_user_detects_taken_username_or_email() -> Result<()> {
+    let Some(db) = common::TestDb::setup().await else {
+        return Ok(());
+    };
+    let pool = &db.pool;
+
+    UserService::insert_user_returning(pool, "taken", "taken@example.com").await?;
+
+    assert!(can_insert_user(pool, "free", "free@example.com").await?);
+    assert!(!can_insert_user(pool, "taken", "free@example.com").await?);
+    assert!(!can_insert_user(pool, "free", "taken@example.com").await?);
+
+    db.teardown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_find_duplicate_profiles_is_empty_on_healthy_database() -> Result<()> {
+    let Some(db) = common::TestDb::setup().await else {
+        return Ok(());
+    };
+    let pool = &db.pool;
+
+    UserProfileService::create_user_with_profile(pool).await?;
+    UserProfileService::create_user_with_profile(pool).await?;
+    assert!(find_duplicate_profiles(pool).await?.is_empty());
+
+    db.teardown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_find_orphaned_profiles_detects_missing_user() -> Result<()> {
+    let Some(db) = common::TestDb::setup().await else {
+        return Ok(());
+    };
+    let pool = &db.pool;
+
+    UserProfileService::create_user_with_profile(pool).await?;
+    assert!(find_orphaned_profiles(pool).await?.is_empty());
+
+    // 在同一个连接上关闭外键检查，模拟批量导入时写入的孤立记录
+    let mut conn = pool.primary.acquire().await?;
+    sqlx::query("SET FOREIGN_KEY_CHECKS = 0").execute(&mut *conn).await?;
+    let orphan_id = sqlx::query(&pool.schema.sql("INSERT INTO profiles (user_id, full_name) VALUES (?, ?)"))
+        .bind(u64::MAX)
+        .bind("Orphan")
+        .execute(&mut *conn)
+        .await?
+        .last_insert_id();
+    sqlx::query("SET FOREIGN_KEY_CHECKS = 1").execute(&mut *conn).await?;
+    drop(conn);
+
+    let orphans = find_orphaned_profiles(pool).await?;
+    assert_eq!(orphans.len(), 1);
+    assert_eq!(orphans[0].id, orphan_id);
+    assert_eq!(orphans[0].user_id, u64::MAX);
+
+    db.teardown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_select_users_by_ids_preserves_order() -> Result<()> {
+    let Some(db) = common::TestDb::setup().await else {
+        return Ok(());
+    };
+    let pool = &db.pool;
+
+    let first = UserService::insert_user(pool).await?;
+    let second = UserService::insert_user(pool).await?;
+    let missing = u64::MAX;
+
+    let users = select_users_by_ids(pool, &[second, missing, first, second]).await?;
+    let ids: Vec<Option<u64>> = users.iter().map(|user| user.as_ref().map(|u| u.id)).collect();
+    assert_eq!(ids, vec![Some(second), None, Some(first), Some(second)]);
+    assert!(select_users_by_ids(pool, &[]).await?.is_empty());
+
+    db.teardown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_search_profiles_by_name_escapes_wildcards() -> Result<()> {
+    let Some(db) = common::TestDb::setup().await else {
+        return Ok(());
+    };
+    let pool = &db.pool;
+
+    for full_name in ["Zhang_San", "ZhangXSan", "Li Zhang"] {
+        let user_id = UserService::insert_user(pool).await?;
+        ProfileService::insert_profile(pool, user_id, full_name, None, None).await?;
+    }
+
+    let names = |profiles: Vec<sqlx_example::Profile>| -> Vec<String> {
+        profiles.into_iter().map(|p| p.full_name).collect()
+    };
+    let found = names(search_profiles_by_name(pool, "Zhang", 10).await?);
+    assert_eq!(found.len(), 3);
+    assert_eq!(found[0], "Li Zhang");
+    // _ 按字面匹配，不会匹配到 ZhangXSan
+    assert_eq!(names(search_profiles_by_name(pool, "g_S", 10).await?), ["Zhang_San"]);
+    assert_eq!(search_profiles_by_name(pool, "Zhang", 1).await?.len(), 1);
+
+    db.teardown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_select_recently_updated_users_filters_by_updated_at() -> Result<()> {
+    let Some(db) = common::TestDb::setup().await else {
+        return Ok(());
+    };
+    let pool = &db.pool;
+
+    let old = Utc.with_ymd_and_hms(2001, 1, 1, 0, 0, 0).unwrap();
+    for i in 0..2 {
+        sqlx::query(&pool.schema.sql("INSERT INTO users (username, email, updated_at) VALUES (?, ?, ?)"))
+            .bind(format!("stale_{}", i))
+            .bind(format!("stale_{}@example.com", i))
+            .bind(old)
+            .execute(&pool.primary)
+            .await?;
+    }
+    let fresh = UserService::insert_user(pool).await?;
+
+    let found: Vec<u64> = select_recently_updated_users(pool, Utc::now() - Duration::minutes(5))
+        .await?
+        .iter()
+        .map(|u| u.id)
+        .collect();
+    assert_eq!(found, vec![fresh]);
+    assert_eq!(select_recently_updated_users(pool, old).await?.len(), 3);
+
+    db.teardown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_fetch_scalar_helpers() -> Result<()> {
+    let Some(db) = common::TestDb::setup().await else {
+        return Ok(());
+    };
+    let pool = &db.pool;
+
+    let user_id = UserService::insert_user(pool).await?;
+    assert_eq!(fetch_scalar::<i64>(pool, "SELECT COUNT(*) FROM users").await?, 1);
+    let exists_sql = "SELECT EXISTS(SELECT 1 FROM users WHERE id = ?)";
+    assert!(fetch_scalar_with::<bool, _>(pool, exists_sql, user_id).await?);
+    assert!(!fetch_scalar_with::<bool, _>(pool, exists_sql, u64::MAX).await?);
+
+    db.teardown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_timestamps_decode_as_utc() -> Result<()> {
+    let Some(db) = common::TestDb::setup().await else {
+        return Ok(());
+    };
+    let pool = &db.pool;
+
+    // sqlx 连接时把会话时区设为 UTC，TIMESTAMP 的读写都不经过本地时区
+    let time_zone: String = sqlx::query_scalar("SELECT @@session.time_zone").fetch_one(&pool.primary).await?;
+    assert_eq!(time_zone, "+00:00");
+
+    // 2024-03-10 02:30 在美国东部时间处于夏令时切换的空档
+    let result = sqlx::query(&pool.schema.sql("INSERT INTO users (username, email, created_at) VALUES (?, ?, ?)"))
+        .bind("dst")
+        .bind("dst@example.com")
+        .bind("2024-03-10 02:30:00")
+        .execute(&pool.primary)
+        .await?;
+    let user = select_user_by_id(pool, result.last_insert_id()).await?.unwrap();
+    assert_eq!(user.created_at, Utc.with_ymd_and_hms(2024, 3, 10, 2, 30, 0).unwrap());
+
+    let naive: NaiveDateTime = sqlx::query_scalar(&pool.schema.sql("SELECT created_at FROM users WHERE id = ?"))
+        .bind(user.id)
+        .fetch_one(&pool.primary)
+        .await?;
+    assert_eq!(to_utc(naive), user.created_at);
+
+    db.teardown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_warmup_pool_opens_connections() -> Result<()> {
+    let Some(db) = common::TestDb::setup().await else {
+        return Ok(());
+    };
+    let pool = &db.pool;
+
+    // 测试连接池最多 2 个连接，请求更多时按上限截断而不是等待超时
+    warmup_pool(&pool.primary, 10).await?;
+    assert_eq!(pool.primary.size(), 2);
+
+    db.teardown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_paginated_users_report_effective_limit() -> Result<()> {
+    let Some(db) = common::TestDb::setup().await else {
+        return Ok(());
+    };
+    let pool = &db.pool;
+
+    UserService::insert_user(pool).await?;
+    UserService::insert_user(pool).await?;
+
+    // 超过单页上限的 limit 被截断，返回的 Page 和 JSON 中都是实际使用的值
+    let page = select_users_paginated(pool, MAX_PAGE_LIMIT + 5, 1).await?;
+    assert_eq!((page.data.len(), page.total, page.limit, page.offset), (1, 2, MAX_PAGE_LIMIT, 1));
+    let json: serde_json::Value = serde_json::from_str(&users_page_json(pool, MAX_PAGE_LIMIT + 5, 0).await?)?;
+    assert_eq!(json["limit"], MAX_PAGE_LIMIT);
+    assert_eq!(json["data"].as_array().map(Vec::len), Some(2));
+
+    db.teardown().await;
+    Ok(())
+}
@@ -0,0 +1,82 @@
+use anyhow::Result;
+
+use sqlx_example::database::{count_profiles, count_users};
+use sqlx_example::{DemoEvent, RunSummary, run_demo};
+
+mod common;
+
+// 事件名称，便于整体比较事件序列
+fn event_name(event: &DemoEvent) -> &'static str {
+    match event {
+        DemoEvent::TableCreated => "TableCreated",
+        DemoEvent::UserInserted { .. } => "UserInserted",
+        DemoEvent::UsersBatchInserted { .. } => "UsersBatchInserted",
+        DemoEvent::UserUpserted { .. } => "UserUpserted",
+        DemoEvent::UsersQueried { .. } => "UsersQueried",
+        DemoEvent::UserUpdated { .. } => "UserUpdated",
+        DemoEvent::OldestUserDeleted => "OldestUserDeleted",
+        DemoEvent::UserWithProfileCreated { .. } => "UserWithProfileCreated",
+        DemoEvent::UserAndProfileUpdated { .. } => "UserAndProfileUpdated",
+        DemoEvent::RollbackTriggered { .. } => "RollbackTriggered",
+        DemoEvent::UserAndProfileDeleted { .. } => "UserAndProfileDeleted",
+        DemoEvent::Verified { .. } => "Verified",
+    }
+}
+
+#[tokio::test]
+async fn test_run_demo_emits_events_in_order() -> Result<()> {
+    let Some(db) = common::TestDb::setup().await else {
+        return Ok(());
+    };
+    let pool = &db.pool;
+
+    let run = run_demo(pool).await?;
+    let names: Vec<&str> = run.events.iter().map(event_name).collect();
+    assert_eq!(
+        names,
+        [
+            "TableCreated",
+            "UserInserted",
+            "UsersBatchInserted",
+            "UserUpserted",
+            "UsersQueried",
+            "UserUpdated",
+            "OldestUserDeleted",
+            "UserWithProfileCreated",
+            "UserAndProfileUpdated",
+            "RollbackTriggered",
+            "RollbackTriggered",
+            "UserAndProfileDeleted",
+            "Verified",
+        ]
+    );
+
+    let events = &run.events;
+    let DemoEvent::UserInserted { id: user_id } = events[1] else { unreachable!() };
+    assert!(matches!(&events[2], DemoEvent::UsersBatchInserted { ids } if ids.len() == 3));
+    // 插入或更新使用批量插入的第一个用户名，只更新邮箱
+    assert!(matches!(events[3], DemoEvent::UserUpserted { created: false, .. }));
+    assert_eq!(events[4], DemoEvent::UsersQueried { count: 4 });
+    assert_eq!(events[5], DemoEvent::UserUpdated { id: user_id });
+    assert_eq!(events[9], DemoEvent::RollbackTriggered { multi_table: false });
+    assert_eq!(events[10], DemoEvent::RollbackTriggered { multi_table: true });
+    // 更新的是第 7 步创建的带 profile 的用户
+    let DemoEvent::UserWithProfileCreated { user_id: profile_user_id, .. } = events[7] else {
+        unreachable!()
+    };
+    assert_eq!(events[8], DemoEvent::UserAndProfileUpdated { id: profile_user_id });
+
+    let users = count_users(pool).await? as usize;
+    let profiles = count_profiles(pool).await? as usize;
+    assert_eq!(events[12], DemoEvent::Verified { users, profiles });
+
+    // 创建：1 个单独插入 + 3 个批量插入 + 1 个带 profile 的用户；
+    // 更新：插入或更新、更新邮箱、多表更新；删除：最早的用户和多表删除；两次回滚
+    assert_eq!(
+        run.summary,
+        RunSummary { users_created: 5, users_updated: 3, users_deleted: 2, rollbacks: 2 }
+    );
+
+    db.teardown().await;
+    Ok(())
+}
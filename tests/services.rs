@@ -0,0 +1,566 @@
+use std::time::Duration;
+
+use anyhow::Result;
+
+use sqlx_example::database::{
+    count_users_by_email, prune_soft_deleted_users, select_active_users, select_all_profiles,
+    select_all_users, select_all_users_tx, select_profile_by_user_id, select_user_by_email,
+    select_user_by_id, select_user_by_id_required,
+};
+use sqlx_example::models::{DeleteReport, NewUser, UpdateProfile, UpdateReport};
+use sqlx_example::services::test_transaction_rollback;
+use sqlx_example::{AppError, ProfileService, UserProfileService, UserService};
+
+mod common;
+
+#[tokio::test]
+async fn test_update_missing_user_returns_not_found() -> Result<()> {
+    let Some(db) = common::TestDb::setup().await else {
+        return Ok(());
+    };
+    let pool = &db.pool;
+
+    let missing_id = u64::MAX;
+    let err = UserService::update_user_email(pool, missing_id).await.unwrap_err();
+    assert!(matches!(err.downcast_ref::<AppError>(), Some(AppError::NotFound(_))));
+
+    let err = UserService::update_username(pool, missing_id, "nobody").await.unwrap_err();
+    assert!(matches!(err.downcast_ref::<AppError>(), Some(AppError::NotFound(_))));
+
+    let err = select_user_by_id_required(pool, missing_id).await.unwrap_err();
+    assert!(matches!(err.downcast_ref::<AppError>(), Some(AppError::NotFound(_))));
+
+    db.teardown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_update_user_email_advances_updated_at() -> Result<()> {
+    let Some(db) = common::TestDb::setup().await else {
+        return Ok(());
+    };
+    let pool = &db.pool;
+
+    let user_id = UserService::insert_user(pool).await?;
+    let before = select_user_by_id(pool, user_id).await?.unwrap();
+
+    // TIMESTAMP 精度为秒，等待超过一秒以便观察到 updated_at 的变化
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    let was_modified = UserService::update_user_email(pool, user_id).await?;
+    let after = select_user_by_id(pool, user_id).await?.unwrap();
+
+    assert!(was_modified);
+    assert!(after.updated_at > before.updated_at);
+
+    db.teardown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_update_emails_bulk_is_atomic() -> Result<()> {
+    let Some(db) = common::TestDb::setup().await else {
+        return Ok(());
+    };
+    let pool = &db.pool;
+
+    let first = UserService::insert_user_returning(pool, "bulk_a", "bulk_a@example.com").await?;
+    let second = UserService::insert_user_returning(pool, "bulk_b", "bulk_b@example.com").await?;
+
+    let updates = vec![
+        (first.id, "bulk_a@new.example.com".to_string()),
+        (second.id, "bulk_b@new.example.com".to_string()),
+    ];
+    assert_eq!(UserService::update_emails_bulk(pool, &updates).await?, 2);
+
+    // 第二条更新与第一条冲突（邮箱唯一），整批都应回滚
+    let conflicting = vec![
+        (first.id, "bulk_same@example.com".to_string()),
+        (second.id, "bulk_same@example.com".to_string()),
+    ];
+    assert!(UserService::update_emails_bulk(pool, &conflicting).await.is_err());
+    let user = select_user_by_id(pool, first.id).await?.unwrap();
+    assert_eq!(user.email, "bulk_a@new.example.com");
+
+    // 邮箱格式错误时不会访问数据库
+    let invalid = vec![(first.id, "not-an-email".to_string())];
+    let err = UserService::update_emails_bulk(pool, &invalid).await.unwrap_err();
+    assert!(matches!(err.downcast_ref::<AppError>(), Some(AppError::Validation(_))));
+
+    db.teardown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_reassign_profile_moves_profile_and_rejects_taken_target() -> Result<()> {
+    let Some(db) = common::TestDb::setup().await else {
+        return Ok(());
+    };
+    let pool = &db.pool;
+
+    let from = UserService::insert_user(pool).await?;
+    let to = UserService::insert_user(pool).await?;
+    let other = UserService::insert_user(pool).await?;
+    ProfileService::insert_profile(pool, from, "Alice", None, None).await?;
+    ProfileService::insert_profile(pool, other, "Bob", None, None).await?;
+
+    ProfileService::reassign_profile(pool, from, to).await?;
+    assert!(select_profile_by_user_id(pool, from).await?.is_none());
+    assert_eq!(select_profile_by_user_id(pool, to).await?.unwrap().full_name, "Alice");
+
+    // 目标用户已有 profile
+    let err = ProfileService::reassign_profile(pool, to, other).await.unwrap_err();
+    assert!(matches!(err.downcast_ref::<AppError>(), Some(AppError::Duplicate(_))));
+    assert_eq!(select_profile_by_user_id(pool, to).await?.unwrap().full_name, "Alice");
+
+    // 目标用户不存在
+    let err = ProfileService::reassign_profile(pool, to, u64::MAX).await.unwrap_err();
+    assert!(matches!(err.downcast_ref::<AppError>(), Some(AppError::NotFound(_))));
+
+    db.teardown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_last_insert_id_is_scoped_to_transaction_connection() -> Result<()> {
+    let Some(db) = common::TestDb::setup().await else {
+        return Ok(());
+    };
+    let pool = &db.pool;
+
+    let mut transaction = pool.primary.begin().await?;
+    let own_id = sqlx::query(&pool.schema.sql("INSERT INTO users (username, email) VALUES (?, ?)"))
+        .bind("scoped_a")
+        .bind("scoped_a@example.com")
+        .execute(&mut *transaction)
+        .await?
+        .last_insert_id();
+
+    // 其他连接上的插入不会改变本事务连接的 LAST_INSERT_ID()
+    let other_id = UserService::insert_user(pool).await?;
+    assert_ne!(own_id, other_id);
+
+    let last_id: u64 = sqlx::query_scalar("SELECT LAST_INSERT_ID()")
+        .fetch_one(&mut *transaction)
+        .await?;
+    assert_eq!(last_id, own_id);
+    transaction.commit().await?;
+
+    db.teardown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_insert_new_user_uses_given_values() -> Result<()> {
+    let Some(db) = common::TestDb::setup().await else {
+        return Ok(());
+    };
+    let pool = &db.pool;
+
+    let new = NewUser {
+        username: "alice".to_string(),
+        email: "alice@example.com".to_string(),
+    };
+    let user_id = UserService::insert_new_user(pool, new).await?;
+    let user = select_user_by_id(pool, user_id).await?.unwrap();
+    assert_eq!((user.username.as_str(), user.email.as_str()), ("alice", "alice@example.com"));
+
+    let blank = NewUser {
+        username: "  ".to_string(),
+        email: "blank@example.com".to_string(),
+    };
+    let err = UserService::insert_new_user(pool, blank).await.unwrap_err();
+    assert!(matches!(err.downcast_ref::<AppError>(), Some(AppError::Validation(_))));
+
+    db.teardown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_create_user_with_profile_returning_returns_linked_rows() -> Result<()> {
+    let Some(db) = common::TestDb::setup().await else {
+        return Ok(());
+    };
+    let pool = &db.pool;
+
+    let (user, profile) = UserProfileService::create_user_with_profile_returning(pool).await?;
+    assert_eq!(profile.user_id, user.id);
+    assert_eq!(select_profile_by_user_id(pool, user.id).await?.unwrap().id, profile.id);
+
+    db.teardown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_get_or_create_user_by_email() -> Result<()> {
+    let Some(db) = common::TestDb::setup().await else {
+        return Ok(());
+    };
+    let pool = &db.pool;
+
+    let (user, created) =
+        UserService::get_or_create_user_by_email(pool, "oauth@example.com", "oauth").await?;
+    assert!(created);
+
+    let (again, created) =
+        UserService::get_or_create_user_by_email(pool, "oauth@example.com", "other").await?;
+    assert!(!created);
+    assert_eq!(again.id, user.id);
+    assert_eq!(again.username, "oauth");
+
+    // 并发创建同一邮箱时，两个请求都应得到同一个用户
+    let (a, b) = tokio::join!(
+        UserService::get_or_create_user_by_email(pool, "race@example.com", "race_a"),
+        UserService::get_or_create_user_by_email(pool, "race@example.com", "race_b"),
+    );
+    let (a, b) = (a?, b?);
+    assert_eq!(a.0.id, b.0.id);
+    assert!(a.1 ^ b.1);
+
+    db.teardown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_update_user_email_if_unchanged_detects_concurrent_change() -> Result<()> {
+    let Some(db) = common::TestDb::setup().await else {
+        return Ok(());
+    };
+    let pool = &db.pool;
+
+    let user = UserService::insert_user_returning(pool, "optimistic", "optimistic@example.com").await?;
+
+    assert!(
+        UserService::update_user_email_if_unchanged(pool, user.id, user.updated_at, "first@example.com")
+            .await?
+    );
+
+    // 等待超过一秒，使第一次更新后的 updated_at 与最初读取的值不同
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    UserService::update_user_email(pool, user.id).await?;
+
+    let stale = user.updated_at;
+    assert!(
+        !UserService::update_user_email_if_unchanged(pool, user.id, stale, "second@example.com")
+            .await?
+    );
+    let current = select_user_by_id(pool, user.id).await?.unwrap();
+    assert_ne!(current.email, "second@example.com");
+
+    db.teardown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_migrate_email_domain_rolls_back_on_conflict() -> Result<()> {
+    let Some(db) = common::TestDb::setup().await else {
+        return Ok(());
+    };
+    let pool = &db.pool;
+
+    let alice = UserService::insert_user_returning(pool, "alice", "alice@old.com").await?;
+    let bob = UserService::insert_user_returning(pool, "bob", "bob@OLD.com").await?;
+    UserService::insert_user_returning(pool, "carol", "carol@other.com").await?;
+
+    assert_eq!(UserService::migrate_email_domain(pool, "old.com", "new.com").await?, 2);
+    assert_eq!(select_user_by_id(pool, alice.id).await?.unwrap().email, "alice@new.com");
+    assert_eq!(select_user_by_id(pool, bob.id).await?.unwrap().email, "bob@new.com");
+
+    // carol@other.com 改写后与已存在的 carol@new.com 冲突，整批回滚
+    UserService::insert_user_returning(pool, "carol2", "carol@new.com").await?;
+    UserService::insert_user_returning(pool, "dave", "dave@other.com").await?;
+    let err = UserService::migrate_email_domain(pool, "other.com", "new.com").await.unwrap_err();
+    assert!(matches!(err.downcast_ref::<AppError>(), Some(AppError::Duplicate(_))));
+    let dave = select_user_by_email(pool, "dave@other.com").await?;
+    assert!(dave.is_some());
+
+    db.teardown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_migrate_email_domain_case_only_change() -> Result<()> {
+    let Some(db) = common::TestDb::setup().await else {
+        return Ok(());
+    };
+    let pool = &db.pool;
+
+    let alice = UserService::insert_user_returning(pool, "alice", "alice@OLD.com").await?;
+    let bob = UserService::insert_user_returning(pool, "bob", "bob@OLD.com").await?;
+
+    // 只改变大小写时每行都与自己"相等"，不应被当作冲突
+    assert_eq!(UserService::migrate_email_domain(pool, "OLD.com", "old.com").await?, 2);
+    assert_eq!(select_user_by_id(pool, alice.id).await?.unwrap().email, "alice@old.com");
+    assert_eq!(select_user_by_id(pool, bob.id).await?.unwrap().email, "bob@old.com");
+    // 新旧域名相同也不会报冲突
+    assert_eq!(UserService::migrate_email_domain(pool, "old.com", "old.com").await?, 2);
+
+    db.teardown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_update_profile_fields_only_touches_given_fields() -> Result<()> {
+    let Some(db) = common::TestDb::setup().await else {
+        return Ok(());
+    };
+    let pool = &db.pool;
+
+    let user_id = UserService::insert_user(pool).await?;
+    ProfileService::insert_profile(
+        pool,
+        user_id,
+        "Alice",
+        Some("旧简介"),
+        Some("https://example.com/a.png"),
+    )
+    .await?;
+
+    let update = UpdateProfile {
+        bio: Some(Some("新简介".to_string())),
+        avatar_url: Some(None),
+        ..Default::default()
+    };
+    ProfileService::update_profile_fields(pool, user_id, &update).await?;
+
+    let profile = select_profile_by_user_id(pool, user_id).await?.unwrap();
+    assert_eq!(profile.full_name, "Alice");
+    assert_eq!(profile.bio.as_deref(), Some("新简介"));
+    assert_eq!(profile.avatar_url, None);
+
+    let err = ProfileService::update_profile_fields(pool, user_id, &UpdateProfile::default())
+        .await
+        .unwrap_err();
+    assert!(matches!(err.downcast_ref::<AppError>(), Some(AppError::Validation(_))));
+
+    db.teardown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_delete_user_cascade_reports_profiles() -> Result<()> {
+    let Some(db) = common::TestDb::setup().await else {
+        return Ok(());
+    };
+    let pool = &db.pool;
+
+    let (user_id, _) = UserProfileService::create_user_with_profile(pool).await?;
+    let report = UserService::delete_user_cascade(pool, user_id).await?;
+    assert_eq!(report, DeleteReport { user_deleted: true, profiles_deleted: 1 });
+    assert!(select_profile_by_user_id(pool, user_id).await?.is_none());
+
+    let report = UserService::delete_user_cascade(pool, user_id).await?;
+    assert_eq!(report, DeleteReport { user_deleted: false, profiles_deleted: 0 });
+
+    db.teardown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_update_user_and_profile_reports_rows() -> Result<()> {
+    let Some(db) = common::TestDb::setup().await else {
+        return Ok(());
+    };
+    let pool = &db.pool;
+
+    let (user_id, _) = UserProfileService::create_user_with_profile(pool).await?;
+    let report = UserProfileService::update_user_and_profile(pool, user_id).await?;
+    assert_eq!(report, UpdateReport { user_rows: 1, profile_rows: 1 });
+
+    // 没有 profile 的用户：用户行被更新，profile 行数为 0
+    let user_id = UserService::insert_user(pool).await?;
+    let report = UserProfileService::update_user_and_profile(pool, user_id).await?;
+    assert_eq!(report, UpdateReport { user_rows: 1, profile_rows: 0 });
+
+    db.teardown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_upsert_profile_is_idempotent() -> Result<()> {
+    let Some(db) = common::TestDb::setup().await else {
+        return Ok(());
+    };
+    let pool = &db.pool;
+
+    let user_id = UserService::insert_user(pool).await?;
+    let first = ProfileService::upsert_profile(pool, user_id, "Alice", None, None).await?;
+    let second =
+        ProfileService::upsert_profile(pool, user_id, "Alice Smith", Some("简介"), None).await?;
+    assert_eq!(first, second);
+
+    let profiles = select_all_profiles(pool).await?;
+    assert_eq!(profiles.len(), 1);
+    assert_eq!(profiles[0].full_name, "Alice Smith");
+    assert_eq!(profiles[0].bio.as_deref(), Some("简介"));
+
+    let err = ProfileService::upsert_profile(pool, u64::MAX, "Nobody", None, None).await.unwrap_err();
+    assert!(matches!(err.downcast_ref::<AppError>(), Some(AppError::ForeignKeyViolation(_))));
+
+    db.teardown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_insert_profile_for_missing_user_returns_foreign_key_violation() -> Result<()> {
+    let Some(db) = common::TestDb::setup().await else {
+        return Ok(());
+    };
+    let pool = &db.pool;
+
+    let err = ProfileService::insert_profile(pool, u64::MAX, "Nobody", None, None).await.unwrap_err();
+    assert!(matches!(err.downcast_ref::<AppError>(), Some(AppError::ForeignKeyViolation(_))));
+    assert!(select_all_profiles(pool).await?.is_empty());
+
+    db.teardown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_prune_soft_deleted_users() -> Result<()> {
+    let Some(db) = common::TestDb::setup().await else {
+        return Ok(());
+    };
+    let pool = &db.pool;
+
+    let stale = UserService::insert_user(pool).await?;
+    let recent = UserService::insert_user(pool).await?;
+    let kept = UserService::insert_user(pool).await?;
+    assert!(UserService::soft_delete_user(pool, stale).await?);
+    assert!(UserService::soft_delete_user(pool, recent).await?);
+    assert!(!UserService::soft_delete_user(pool, recent).await?);
+    sqlx::query(&pool.schema.sql("UPDATE users SET deleted_at = NOW() - INTERVAL 2 DAY WHERE id = ?"))
+        .bind(stale)
+        .execute(&pool.primary)
+        .await?;
+
+    assert_eq!(prune_soft_deleted_users(pool, Duration::from_secs(24 * 3600)).await?, 1);
+    assert!(select_user_by_id(pool, stale).await?.is_none());
+    assert!(select_user_by_id(pool, recent).await?.is_some());
+    assert!(select_user_by_id(pool, kept).await?.is_some());
+
+    db.teardown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_clone_user_copies_profile() -> Result<()> {
+    let Some(db) = common::TestDb::setup().await else {
+        return Ok(());
+    };
+    let pool = &db.pool;
+
+    let (source_id, _) = UserProfileService::create_user_with_profile(pool).await?;
+    let clone_id = UserProfileService::clone_user(pool, source_id).await?;
+    assert_ne!(clone_id, source_id);
+
+    let source = select_profile_by_user_id(pool, source_id).await?.unwrap();
+    let cloned = select_profile_by_user_id(pool, clone_id).await?.unwrap();
+    assert_eq!(cloned.full_name, source.full_name);
+    assert_eq!(cloned.bio, source.bio);
+    assert_eq!(cloned.avatar_url, source.avatar_url);
+
+    // 没有 profile 的用户只复制用户本身
+    let bare_id = UserService::insert_user(pool).await?;
+    let bare_clone = UserProfileService::clone_user(pool, bare_id).await?;
+    assert!(select_user_by_id(pool, bare_clone).await?.is_some());
+    assert!(select_profile_by_user_id(pool, bare_clone).await?.is_none());
+
+    let err = UserProfileService::clone_user(pool, u64::MAX).await.unwrap_err();
+    assert!(matches!(err.downcast_ref::<AppError>(), Some(AppError::NotFound(_))));
+
+    db.teardown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_set_user_active_filters_active_users() -> Result<()> {
+    let Some(db) = common::TestDb::setup().await else {
+        return Ok(());
+    };
+    let pool = &db.pool;
+
+    let active = UserService::insert_user(pool).await?;
+    let inactive = UserService::insert_user(pool).await?;
+    assert!(select_user_by_id(pool, inactive).await?.unwrap().is_active);
+
+    UserService::set_user_active(pool, inactive, false).await?;
+    let ids: Vec<u64> = select_active_users(pool).await?.iter().map(|u| u.id).collect();
+    assert_eq!(ids, vec![active]);
+    // 停用的用户仍然存在
+    assert_eq!(select_all_users(pool).await?.len(), 2);
+
+    UserService::set_user_active(pool, inactive, true).await?;
+    assert_eq!(select_active_users(pool).await?.len(), 2);
+
+    let err = UserService::set_user_active(pool, u64::MAX, false).await.unwrap_err();
+    assert!(matches!(err.downcast_ref::<AppError>(), Some(AppError::NotFound(_))));
+
+    db.teardown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_transaction_rollback_keeps_single_email() -> Result<()> {
+    let Some(db) = common::TestDb::setup().await else {
+        return Ok(());
+    };
+    let pool = &db.pool;
+
+    let user = UserService::insert_user_returning(pool, "rollback", "rollback@example.com").await?;
+    assert!(test_transaction_rollback(pool).await?);
+    assert_eq!(count_users_by_email(pool, &user.email).await?, 1);
+    assert_eq!(count_users_by_email(pool, "nobody@example.com").await?, 0);
+
+    db.teardown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_select_all_users_tx_sees_uncommitted_rows() -> Result<()> {
+    let Some(db) = common::TestDb::setup().await else {
+        return Ok(());
+    };
+    let pool = &db.pool;
+
+    UserService::insert_user(pool).await?;
+    let mut transaction = pool.primary.begin().await?;
+    sqlx::query(&pool.schema.sql("INSERT INTO users (username, email) VALUES (?, ?)"))
+        .bind("uncommitted")
+        .bind("uncommitted@example.com")
+        .execute(&mut *transaction)
+        .await?;
+
+    // 事务内能看到未提交的用户，连接池的其他连接看不到
+    assert_eq!(select_all_users_tx(&mut transaction, &pool.schema).await?.len(), 2);
+    assert_eq!(select_all_users(pool).await?.len(), 1);
+
+    transaction.rollback().await?;
+    assert_eq!(select_all_users(pool).await?.len(), 1);
+
+    db.teardown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_upsert_user_rejects_email_of_other_user() -> Result<()> {
+    let Some(db) = common::TestDb::setup().await else {
+        return Ok(());
+    };
+    let pool = &db.pool;
+
+    let owner = UserService::insert_user_returning(pool, "owner", "owner@example.com").await?;
+
+    // 新用户名配上其他用户的邮箱：不能改写那个用户
+    let err = UserService::upsert_user(pool, "newcomer", "owner@example.com").await.unwrap_err();
+    assert!(matches!(err.downcast_ref::<AppError>(), Some(AppError::Duplicate(_))));
+    let unchanged = select_user_by_id(pool, owner.id).await?.unwrap();
+    assert_eq!(unchanged.username, "owner");
+    assert!(select_user_by_email(pool, "owner@example.com").await?.is_some_and(|u| u.id == owner.id));
+
+    // 用户名和邮箱属于同一个用户时仍按更新处理
+    let (id, created) = UserService::upsert_user(pool, "owner", "owner@example.com").await?;
+    assert_eq!((id, created), (owner.id, false));
+
+    db.teardown().await;
+    Ok(())
+}